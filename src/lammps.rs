@@ -12,6 +12,8 @@ pub struct LammpsTrajectoryFrame {
     pub timestep: usize,
     /// ITEM: ATOMS
     pub atoms: HashMap<usize, LammpsAtom>,
+    /// ITEM: BOX BOUNDS (fully periodic box only)
+    pub lattice: Option<Lattice>,
 }
 
 /// Minimal Atom representation for LAMMPS.
@@ -124,9 +126,72 @@ fn test_read_box_data() {
 -200.487 200.487
 ";
     let (_, x) = read_box_data(txt).unwrap();
+    let lat = x.to_lattice().unwrap();
+    assert_relative_eq!(lat.volume(), 400.974f64.powi(3), epsilon = 1e-3);
+
+    let txt = "ITEM: BOX BOUNDS xy xz yz pp pp ff
+0.0 10.0 1.0
+0.0 10.0 0.0
+0.0 10.0 0.0
+";
+    let (_, x) = read_box_data(txt).unwrap();
+    assert!(x.to_lattice().is_none());
 }
 // 41dd0da2 ends here
 
+// [[file:../trajectory.note::5f0d2b86][5f0d2b86]]
+use gchemol::{Atom, Lattice, Molecule};
+
+impl BoxData {
+    /// Convert box bounds into lattice. Return None if the box is not
+    /// periodic in all directions.
+    fn to_lattice(&self) -> Option<Lattice> {
+        // pp pp pp, or xy xz yz pp pp pp for triclinic box
+        let flags: Vec<_> = self.t.split_whitespace().collect();
+        if flags.len() < 3 || flags[flags.len() - 3..].iter().any(|x| *x != "pp") {
+            return None;
+        }
+        let parse = |s: &str| -> Option<Vec<f64>> { s.split_whitespace().map(|x| x.parse().ok()).collect() };
+        let (a, b, c) = (parse(&self.a)?, parse(&self.b)?, parse(&self.c)?);
+        if [&a, &b, &c].iter().any(|x| x.len() < 2) {
+            return None;
+        }
+        // tilt factors for triclinic box
+        let tilt = |x: &[f64]| x.get(2).copied().unwrap_or(0.0);
+        let (xy, xz, yz) = (tilt(&a), tilt(&b), tilt(&c));
+        // NOTE: the bounds of triclinic box are for its bounding box
+        let xlo = a[0] - [0.0, xy, xz, xy + xz].into_iter().fold(f64::MAX, f64::min);
+        let xhi = a[1] - [0.0, xy, xz, xy + xz].into_iter().fold(f64::MIN, f64::max);
+        let ylo = b[0] - yz.min(0.0);
+        let yhi = b[1] - yz.max(0.0);
+        let (zlo, zhi) = (c[0], c[1]);
+
+        let mut lat = Lattice::new([[xhi - xlo, 0.0, 0.0], [xy, yhi - ylo, 0.0], [xz, yz, zhi - zlo]]);
+        lat.set_origin([xlo, ylo, zlo]);
+        Some(lat)
+    }
+}
+
+impl LammpsTrajectoryFrame {
+    /// Convert to `Molecule` using element symbols for atom types
+    /// (`elements[0]` for type 1, and so on). The atom ids are kept as
+    /// atom serial numbers, and the timestep is kept in title.
+    pub fn to_molecule(&self, elements: &[String]) -> Result<Molecule> {
+        let mut mol = Molecule::new(&format!("Timestep: {}", self.timestep));
+        for (&id, atom) in self.atoms.iter().sorted_by_key(|(&id, _)| id) {
+            let sym = elements
+                .get(atom.type_id.wrapping_sub(1))
+                .ok_or(format_err!("no element defined for atom type {}", atom.type_id))?;
+            mol.add_atom(id, Atom::new(sym.as_str(), atom.xyz));
+        }
+        if let Some(lat) = self.lattice {
+            mol.set_lattice(lat);
+        }
+        Ok(mol)
+    }
+}
+// 5f0d2b86 ends here
+
 // [[file:../trajectory.note::*src][src:1]]
 // ITEM: ATOMS id type x y z c_eng c_cn c_cnt c_cna
 fn read_atom_header(s: &str) -> IResult<&str, &str> {
@@ -240,6 +305,7 @@ fn read_lammps_dump_frame(input: &str) -> IResult<&str, LammpsTrajectoryFrame> {
         let mut frame = LammpsTrajectoryFrame::default();
         frame.timestep = frame_data.timestep;
         frame.atoms = atoms;
+        frame.lattice = box_data.to_lattice();
         frame
     };
 
@@ -271,6 +337,14 @@ fn test_parser() -> Result<()> {
     let frames = parse_lammps_dump_file(fname.as_ref())?;
     assert_eq!(frames.count(), 3);
 
+    let frame = parse_lammps_dump_file(fname.as_ref())?.next().unwrap();
+    let elements = vec!["Pt".to_string()];
+    let mol = frame.to_molecule(&elements)?;
+    assert_eq!(mol.natoms(), frame.atoms.len());
+    assert_eq!(mol.title(), "Timestep: 0");
+    assert!(mol.lattice.is_some());
+    assert!(frame.to_molecule(&[]).is_err());
+
     Ok(())
 }
 // d25b8ce6 ends here
@@ -19,6 +19,7 @@ mod lammps;
 mod lindemann;
 // mod part;
 mod reaction;
+mod reaxff;
 mod rings;
 
 // mod atoms;
@@ -36,6 +37,9 @@ mod common {
     pub use gut::prelude::*;
 }
 use common::*;
+
+/// Species history of selected atoms in reactive trajectory
+pub use reaction::{track_atom_fates, AtomFateTracker, SpeciesInterval};
 // 16fef675 ends here
 
 // [[file:../trajectory.note::61448511][61448511]]
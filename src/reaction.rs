@@ -6,18 +6,88 @@ use gchemol::Molecule;
 // [[file:../trajectory.note::29d234b7][29d234b7]]
 mod algo;
 mod base;
+mod bonding;
+mod checkpoint;
+mod fate;
+mod filter;
 mod io;
+mod kinetics;
+mod lifetime;
+mod network;
 mod options;
+mod pbc;
+mod population;
+mod report;
+mod species;
+mod summary;
 
 pub mod cli;
 // 29d234b7 ends here
 
 // [[file:../trajectory.note::707e344d][707e344d]]
+use std::collections::BTreeMap;
+
 /// Return reactants or products composition in string formulation. For
-/// example, for two molecules in `mols`, returns "H2 + CH4"
+/// example, for three molecules in `mols`, returns "CH4 + 2 H2"
 fn get_composition<'a>(mols: impl IntoIterator<Item = &'a Molecule>) -> String {
-    let formula_list: HashSet<_> = mols.into_iter().map(|mol| mol.formula()).collect();
-    formula_list.into_iter().join(" + ")
+    let formulas = mols.into_iter().map(|mol| mol.formula()).collect_vec();
+    format_species(&formulas)
+}
+
+/// Format species `formulas` with stoichiometric coefficients for
+/// repeated ones, such as "CH4 + 2 H". The species are sorted in Hill
+/// order.
+fn format_species<S: AsRef<str>>(formulas: &[S]) -> String {
+    let mut counts: BTreeMap<Vec<(String, usize)>, (&str, usize)> = BTreeMap::new();
+    for formula in formulas {
+        let formula = formula.as_ref();
+        counts.entry(hill_order_key(formula)).or_insert((formula, 0)).1 += 1;
+    }
+    counts
+        .into_values()
+        .map(|(formula, n)| if n > 1 { format!("{n} {formula}") } else { formula.to_string() })
+        .join(" + ")
+}
+
+/// Return balanced chemical equation from formulas of `reactants` and
+/// `products`, such as "2 H => H2".
+fn format_equation<S: AsRef<str>>(reactants: &[S], products: &[S]) -> String {
+    format!("{} => {}", format_species(reactants), format_species(products))
+}
+
+/// Sorting key for formula in Hill order: compare element symbols in
+/// formula one by one, then their counts.
+fn hill_order_key(formula: &str) -> Vec<(String, usize)> {
+    use regex::Regex;
+    use std::sync::OnceLock;
+
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r"([A-Z][a-z]*)(\d*)").unwrap());
+    re.captures_iter(formula)
+        .map(|x| (x[1].to_string(), x[2].parse().unwrap_or(1)))
+        .collect()
+}
+
+/// Return true if `reactants` in `mol1` and `products` in `mol2` consist
+/// of the same atoms, and each of them is a whole fragment closed under
+/// bonds in its frame. Unbalanced reaction could be caused by bonding
+/// artifacts.
+fn is_balanced(mol1: &Molecule, mol2: &Molecule, reactants: &[Molecule], products: &[Molecule]) -> bool {
+    let atoms = |mols: &[Molecule]| mols.iter().flat_map(|mol| mol.numbers()).sorted().collect_vec();
+    let is_closed = |mol: &Molecule, frag: &Molecule| {
+        let atoms: std::collections::HashSet<usize> = frag.numbers().collect();
+        atoms.iter().all(|&a| mol.connected(a).all(|b| atoms.contains(&b)))
+    };
+    atoms(reactants) == atoms(products)
+        && reactants.iter().all(|x| is_closed(mol1, x))
+        && products.iter().all(|x| is_closed(mol2, x))
+}
+
+#[test]
+fn test_format_equation() {
+    assert_eq!(format_equation(&["H", "H"], &["H2"]), "2 H => H2");
+    assert_eq!(format_equation(&["CH4", "H", "C2H6"], &["CH3", "H2", "C2H6"]), "CH4 + C2H6 + H => CH3 + C2H6 + H2");
+    assert_eq!(format_species(&["OH", "H2O", "OH"]), "H2O + 2 OH");
 }
 // 707e344d ends here
 
@@ -34,13 +104,76 @@ fn get_bonding_changes(mol1: &Molecule, mol2: &Molecule) -> [HashSet<[usize; 2]>
     [forming, breaking]
 }
 
+/// Split bonding changes between `mol1` and `mol2` into independent
+/// events. Two bonding changes belong to the same event if they are
+/// connected by reactant fragments in `mol1` or product fragments in
+/// `mol2`.
+fn split_bonding_changes(mol1: &Molecule, mol2: &Molecule) -> Vec<[HashSet<[usize; 2]>; 2]> {
+    // fragments labeled by (frame, smallest atom) as nodes of disjoint sets
+    type Node = (usize, usize);
+    fn find(parent: &mut HashMap<Node, Node>, x: Node) -> Node {
+        let p = *parent.entry(x).or_insert(x);
+        if p == x {
+            x
+        } else {
+            let root = find(parent, p);
+            parent.insert(x, root);
+            root
+        }
+    }
+
+    let mut labels: HashMap<Node, Node> = HashMap::new();
+    let mut fragment_label = |k: usize, a: usize| {
+        let mol = if k == 1 { mol1 } else { mol2 };
+        *labels
+            .entry((k, a))
+            .or_insert_with(|| (k, mol.connected_fragment_atoms(a).min().unwrap_or(a)))
+    };
+
+    let [forming, breaking] = get_bonding_changes(mol1, mol2);
+    let mut parent = HashMap::new();
+    let mut roots = vec![];
+    for &[u, v] in forming.iter().chain(&breaking) {
+        let nodes = [
+            fragment_label(1, u),
+            fragment_label(1, v),
+            fragment_label(2, u),
+            fragment_label(2, v),
+        ];
+        let root = find(&mut parent, nodes[0]);
+        for x in &nodes[1..] {
+            let r = find(&mut parent, *x);
+            parent.insert(r, root);
+        }
+        roots.push(nodes[0]);
+    }
+
+    let mut events: BTreeMap<Node, [HashSet<[usize; 2]>; 2]> = BTreeMap::new();
+    let changes = forming.iter().map(|&b| (0, b)).chain(breaking.iter().map(|&b| (1, b)));
+    for ((k, bond), node) in changes.zip(roots) {
+        let root = find(&mut parent, node);
+        events.entry(root).or_default()[k].insert(bond);
+    }
+    events.into_values().collect()
+}
+
 /// Detects reaction between `mol1` and `mol2` from bond connectivity
 /// changes. Returns reactants and products in list of `Molecule`
 /// objects
 fn get_reaction_mechanism(mol1: &Molecule, mol2: &Molecule) -> Result<([Vec<Molecule>; 2], [HashSet<[usize; 2]>; 2])> {
+    let changes = get_bonding_changes(mol1, mol2);
+    get_reaction_mechanism_from(mol1, mol2, changes)
+}
+
+/// Detects reaction between `mol1` and `mol2` from bonding changes
+/// (forming and breaking bonds).
+fn get_reaction_mechanism_from(
+    mol1: &Molecule,
+    mol2: &Molecule,
+    [forming, breaking]: [HashSet<[usize; 2]>; 2],
+) -> Result<([Vec<Molecule>; 2], [HashSet<[usize; 2]>; 2])> {
     ensure!(mol1.matching_configuration(mol2), "invalid molecule pair for reaction!");
 
-    let [forming, breaking] = get_bonding_changes(mol1, mol2);
     let mut reactants = HashSet::new();
     let mut products = HashSet::new();
 
@@ -72,8 +205,15 @@ fn get_reaction_mechanism(mol1: &Molecule, mol2: &Molecule) -> Result<([Vec<Mole
 
     let reactants: Option<Vec<Molecule>> = reactants.iter().map(|x| mol1.get_sub_molecule(x)).collect();
     let products: Option<Vec<Molecule>> = products.iter().map(|x| mol2.get_sub_molecule(x)).collect();
-    let reactants = reactants.unwrap_or_default();
-    let products = products.unwrap_or_default();
+    let mut reactants = reactants.unwrap_or_default();
+    let mut products = products.unwrap_or_default();
+    // NOTE: fragments could be split across the cell boundary
+    if let Some(lat) = mol1.lattice {
+        reactants.iter_mut().for_each(|mol| pbc::unwrap_fragments_with(mol, &lat));
+    }
+    if let Some(lat) = mol2.lattice {
+        products.iter_mut().for_each(|mol| pbc::unwrap_fragments_with(mol, &lat));
+    }
 
     Ok(([reactants, products], [forming, breaking]))
 }
@@ -91,14 +231,84 @@ pub fn get_reaction_composition(mol1: &Molecule, mol2: &Molecule) -> Result<Stri
     Ok(reaction_composition)
 }
 
+/// Return atom map from `reactants` to `products` in atom serial
+/// numbers. Each item is a triple of atom serial number, index of
+/// reactant and index of product containing this atom.
+fn get_atom_map(reactants: &[Vec<usize>], products: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let product_index: HashMap<usize, usize> = products
+        .iter()
+        .enumerate()
+        .flat_map(|(k, atoms)| atoms.iter().map(move |&a| (a, k)))
+        .collect();
+    reactants
+        .iter()
+        .enumerate()
+        .flat_map(|(k, atoms)| atoms.iter().map(move |&a| (a, k)))
+        .filter_map(|(a, k)| Some(vec![a, k, *product_index.get(&a)?]))
+        .collect()
+}
+
 pub fn get_reaction(mol1: &Molecule, mol2: &Molecule, reaction_species_dir: Option<&Path>) -> Result<Reaction> {
+    let mechanism = get_reaction_mechanism(mol1, mol2)?;
+    let mut species = ReactionSpecies::new();
+    let reaction = new_reaction(mol1, mol2, mechanism, Some(&mut species))?;
+    if let Some(dir) = reaction_species_dir {
+        write_reaction_species(dir, &species)?;
+    }
+    Ok(reaction)
+}
+
+/// Reactants and products of reactions with their fingerprints as keys
+type ReactionSpecies = indexmap::IndexMap<String, Molecule>;
+
+/// Write reaction `species` into `dir`, one file for each fingerprint.
+fn write_reaction_species<'a>(dir: &Path, species: impl IntoIterator<Item = (&'a String, &'a Molecule)>) -> Result<()> {
+    for (fp, mol) in species {
+        let f = dir.join(format!("{fp}.mol2"));
+        io::write_molecules(&f, &[mol.clone()])?;
+    }
+    Ok(())
+}
+
+/// Return all independent reactions between `mol1` and `mol2` selected
+/// by `filter`. The simultaneous reactions between two frames are split
+/// into separate events if they share no reactants or products. The
+/// reactants and products will be collected into `species` if any.
+fn get_reactions(
+    mol1: &Molecule,
+    mol2: &Molecule,
+    filter: &ReactionFilter,
+    mut species: Option<&mut ReactionSpecies>,
+) -> Result<Vec<Reaction>> {
+    let mut reactions = vec![];
+    for changes in split_bonding_changes(mol1, mol2) {
+        let mechanism = get_reaction_mechanism_from(mol1, mol2, changes)?;
+        // NOTE: filter reactions before collecting species
+        let [reactants, products] = &mechanism.0;
+        if filter.matches(reactants, products) {
+            reactions.push(new_reaction(mol1, mol2, mechanism, species.as_deref_mut())?);
+        }
+    }
+    Ok(reactions)
+}
+
+fn new_reaction(
+    mol1: &Molecule,
+    mol2: &Molecule,
+    ([reactants, products], [forming, breaking]): ([Vec<Molecule>; 2], [HashSet<[usize; 2]>; 2]),
+    species: Option<&mut ReactionSpecies>,
+) -> Result<Reaction> {
     // for Molecule.fingerprint method
     use spdkit::prelude::*;
 
     let mut reaction = Reaction::default();
 
-    let ([reactants, products], _) = get_reaction_mechanism(mol1, mol2)?;
-    let mut reaction_composition = String::new();
+    reaction.atom_balanced = is_balanced(mol1, mol2, &reactants, &products);
+    if !reaction.atom_balanced {
+        let reactants = get_composition(&reactants);
+        let products = get_composition(&products);
+        warn!("found reaction with unbalanced atoms: {reactants} => {products}");
+    }
     if !reactants.is_empty() && !products.is_empty() {
         reaction.reactants_composition = get_composition(&reactants);
         reaction.products_composition = get_composition(&products);
@@ -106,12 +316,21 @@ pub fn get_reaction(mol1: &Molecule, mol2: &Molecule, reaction_species_dir: Opti
         reaction.products = products.iter().map(|mol| mol.numbers().collect_vec()).collect();
         reaction.reactants_fingerprints = reactants.iter().map(|mol| mol.fingerprint()).collect();
         reaction.products_fingerprints = products.iter().map(|mol| mol.fingerprint()).collect();
-        // write reactants/products to files
-        if let Some(dir) = reaction_species_dir {
-            for mol in reactants.iter().chain(&products) {
-                let fp = mol.fingerprint();
-                let f = dir.join(format!("{fp}.mol2"));
-                io::write_molecules(&f, &[mol.clone()])?;
+        let canonical = |mols: &[Molecule]| -> (Vec<_>, Vec<_>) {
+            mols.iter().map(species::CanonicalSpecies::new).map(|x| (x.id, x.smiles)).unzip()
+        };
+        (reaction.reactants_canonical_ids, reaction.reactants_smiles) = canonical(&reactants);
+        (reaction.products_canonical_ids, reaction.products_smiles) = canonical(&products);
+        reaction.reactants_formulas = reactants.iter().map(|mol| mol.formula()).collect();
+        reaction.products_formulas = products.iter().map(|mol| mol.formula()).collect();
+        reaction.forming_bonds = forming.iter().map(|&[u, v]| vec![u.min(v), u.max(v)]).collect();
+        reaction.breaking_bonds = breaking.iter().map(|&[u, v]| vec![u.min(v), u.max(v)]).collect();
+        reaction.atom_map = get_atom_map(&reaction.reactants, &reaction.products);
+        // collect reactants/products for writing to files
+        if let Some(species) = species {
+            let fingerprints = reaction.reactants_fingerprints.iter().chain(&reaction.products_fingerprints);
+            for (fp, mol) in fingerprints.zip(reactants.iter().chain(&products)) {
+                species.entry(fp.clone()).or_insert_with(|| mol.clone());
             }
         }
     }
@@ -342,6 +561,8 @@ pub fn get_bonding_events(traj: &Trajectory, noise_event_life: impl Into<Option<
 
 // [[file:../trajectory.note::1276c516][1276c516]]
 pub use base::BondingStates;
+pub use fate::{track_atom_fates, AtomFateTracker, SpeciesInterval};
+pub use filter::ReactionFilter;
 // 1276c516 ends here
 
 // [[file:../trajectory.note::ec1621a3][ec1621a3]]
@@ -367,3 +588,62 @@ fn test_reaction_xyz() -> Result<()> {
     Ok(())
 }
 // ec1621a3 ends here
+
+// [[file:../trajectory.note::4b7e0a93][4b7e0a93]]
+#[test]
+fn test_reaction_mechanism() -> Result<()> {
+    use gchemol::{Atom, Bond};
+
+    // H2 + H => H + H2
+    let atoms = [[0.0, 0.0, 0.0], [0.0, 0.0, 0.7], [0.0, 0.0, 1.4]].map(|p| Atom::new("H", p));
+    let mut mol1 = Molecule::from_atoms(atoms);
+    let mut mol2 = mol1.clone();
+    mol1.add_bond(1, 2, Bond::default());
+    mol2.add_bond(2, 3, Bond::default());
+
+    let reaction = get_reaction(&mol1, &mol2, None)?;
+    assert_eq!(reaction.forming_bonds, vec![vec![2, 3]]);
+    assert_eq!(reaction.breaking_bonds, vec![vec![1, 2]]);
+    assert_eq!(reaction.reactants_composition, "H + H2");
+    assert!(reaction.atom_balanced);
+    assert_eq!(reaction.reactants.len(), 2);
+    assert_eq!(reaction.products.len(), 2);
+    // all atoms are mapped from reactants to products
+    assert_eq!(reaction.atom_map.len(), 3);
+    for x in &reaction.atom_map {
+        assert!(reaction.reactants[x[1]].contains(&x[0]));
+        assert!(reaction.products[x[2]].contains(&x[0]));
+    }
+
+    // two independent H2 + H => H + H2 far away
+    let mut mol1 = mol1;
+    let mut mol2 = mol2;
+    let atoms = [[9.0, 0.0, 0.0], [9.0, 0.0, 0.7], [9.0, 0.0, 1.4]].map(|p| Atom::new("H", p));
+    for (i, atom) in atoms.into_iter().enumerate() {
+        mol1.add_atom(i + 4, atom.clone());
+        mol2.add_atom(i + 4, atom);
+    }
+    mol1.add_bond(4, 5, Bond::default());
+    mol2.add_bond(5, 6, Bond::default());
+    let reactions = get_reactions(&mol1, &mol2, &ReactionFilter::default(), None)?;
+    assert_eq!(reactions.len(), 2);
+    assert_eq!(reactions[0].forming_bonds, vec![vec![2, 3]]);
+    assert_eq!(reactions[1].forming_bonds, vec![vec![5, 6]]);
+    let filter = ReactionFilter { atoms: vec![6], ..Default::default() };
+    let reactions = get_reactions(&mol1, &mol2, &filter, None)?;
+    assert_eq!(reactions.len(), 1);
+    assert_eq!(reactions[0].forming_bonds, vec![vec![5, 6]]);
+    assert_eq!(get_reaction(&mol1, &mol2, None)?.reactants.len(), 4);
+
+    // bonding changes missing their partners in two events, caused by
+    // noise removal on one of the paired bonds: the elements are
+    // balanced by chance, but not the atoms
+    let changes = [[[2, 3]].into(), [[4, 5]].into()];
+    let mechanism = get_reaction_mechanism_from(&mol1, &mol2, changes)?;
+    let reaction = new_reaction(&mol1, &mol2, mechanism, None)?;
+    assert_eq!(reaction.reactants_composition, reaction.products_composition);
+    assert!(!reaction.atom_balanced);
+
+    Ok(())
+}
+// 4b7e0a93 ends here
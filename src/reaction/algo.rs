@@ -1,6 +1,9 @@
 // [[file:../../trajectory.note::0ae3c448][0ae3c448]]
 use super::base::BondingStates;
+use super::bonding::HysteresisBonding;
+use super::io::FrameTime;
 use super::options::ReactionOptions;
+use super::ReactionSpecies;
 
 use crate::common::*;
 // 0ae3c448 ends here
@@ -80,155 +83,606 @@ fn find_reactions(
     mols: &[Molecule],
     states: &BondingStates,
     noise_event_life: usize,
-    // the root dir for writing reaction species
-    reaction_species_dir: Option<&Path>,
+    // the number of context frames on the right side, which could be
+    // smaller than `noise_event_life` for the trailing chunk
+    right_context: usize,
+    options: &ReactionOptions,
+    // collect reaction species for writing
+    mut species: Option<&mut ReactionSpecies>,
     reactive_frames_dir: Option<&Path>,
 ) -> Result<Vec<Reaction>> {
-    let mol_indices = states.find_reactive_bonds(noise_event_life);
+    let nframes = mols.len();
+    let mol_indices = states.find_reactive_bonds_with_context(noise_event_life, right_context);
     let mut reactions = vec![];
     for [i, j] in mol_indices {
         let mi = &mols[i];
         let mj = &mols[j];
-        let mut reaction = super::get_reaction(mi, mj, reaction_species_dir)?;
         let local_frame = j;
         let global_frame = mj.title();
+        // one reaction for each independent event
+        let found = super::get_reactions(mi, mj, &options.reaction_filter, species.as_deref_mut())?;
         // write reactive frames for checking
-        if let Some(dir) = reactive_frames_dir {
+        if let Some(dir) = reactive_frames_dir.filter(|_| !found.is_empty()) {
             let f = dir.join(format!("{global_frame}.mol2"));
-            super::io::write_molecules(&f, &[mi.clone(), mj.clone()])?;
+            let mut frames = [mi.clone(), mj.clone()];
+            if options.center_reactive_frames {
+                let atoms = found.iter().flat_map(|r| r.reactants.iter().flatten().copied()).collect_vec();
+                frames.iter_mut().for_each(|mol| super::pbc::center_atoms_in_cell(mol, &atoms));
+            }
+            super::io::write_molecules(&f, &frames)?;
+        }
+        let frame_time: Option<FrameTime> = mj.properties.load("Frame time").ok();
+        for (n, mut reaction) in found.into_iter().enumerate() {
+            reaction.local_frame = local_frame;
+            reaction.global_frame = global_frame.clone();
+            if let Some(t) = frame_time {
+                reaction.set_frame_time(t);
+            }
+            // not enough frames on the right side for noise removing
+            reaction.low_confidence = local_frame + noise_event_life >= nframes;
+            // frames around the reaction event, limited by frames in this
+            // chunk
+            let k = options.reaction_window.unwrap_or(0);
+            let window = &mols[i.saturating_sub(k)..(j + k + 1).min(nframes)];
+            set_reaction_energies(&mut reaction, mi, mj, window);
+            if let (Some(dir), Some(_)) = (reactive_frames_dir, options.reaction_window) {
+                let f = dir.join(format!("{global_frame}-window-{}.xyz", n + 1));
+                write_reaction_window(&f, window, mj, &reaction, options.reaction_window_shell)?;
+            }
+            reactions.push(reaction);
         }
-        reaction.local_frame = local_frame;
-        reaction.global_frame = global_frame;
-        reactions.push(reaction);
     }
     Ok(reactions)
 }
+
+/// Set energies of `reaction` from frame energies of `mol1` (before
+/// reaction), `mol2` (after reaction) and the `window` frames.
+fn set_reaction_energies(reaction: &mut Reaction, mol1: &Molecule, mol2: &Molecule, window: &[Molecule]) {
+    let energy = |mol: &Molecule| mol.properties.load::<f64>("energy").ok();
+    reaction.energy_before = energy(mol1);
+    reaction.energy_after = energy(mol2);
+    reaction.max_energy = window.iter().filter_map(energy).reduce(f64::max);
+    reaction.energy_barrier = reaction.max_energy.zip(reaction.energy_before).map(|(emax, e)| emax - e);
+}
+
+/// Write `window` frames cropped to atoms in reactants and products of
+/// `reaction` and their neighbors within `shell` in reactive frame `mol`,
+/// into file `f` in extxyz format. The reaction metadata are written in
+/// comment lines.
+fn write_reaction_window(f: &Path, window: &[Molecule], mol: &Molecule, reaction: &Reaction, shell: f64) -> Result<()> {
+    use gchemol::io::formats::ExtxyzFile;
+    use std::collections::BTreeSet;
+
+    let reacting: BTreeSet<usize> = reaction.reactants.iter().chain(&reaction.products).flatten().copied().collect();
+    let atoms: BTreeSet<usize> = mol
+        .numbers()
+        .filter(|&a| {
+            reacting.contains(&a) || reacting.iter().any(|&b| mol.get_distance(a, b).map_or(false, |d| d <= shell))
+        })
+        .collect();
+
+    let equation = super::format_equation(&reaction.reactants_formulas, &reaction.products_formulas);
+    let mut s = String::new();
+    for m in window {
+        let mut sub = m.get_sub_molecule(&atoms).ok_or(format_err!("invalid atoms for reaction window"))?;
+        if let Some(lat) = m.lattice {
+            sub.set_lattice(lat);
+        }
+        let mut meta = format!(
+            "frame={} reaction=\"{equation}\" reaction_frame={} natoms_reacting={}",
+            m.title(),
+            reaction.global_frame,
+            reacting.len()
+        );
+        if let Ok(t) = m.properties.load::<FrameTime>("Frame time") {
+            if let Some(md_step) = t.md_step {
+                write!(meta, " md_step={md_step}")?;
+            }
+            if let Some(time) = t.time {
+                write!(meta, " time={time}")?;
+            }
+        }
+        if let Ok(energy) = m.properties.load::<f64>("energy") {
+            write!(meta, " energy={energy}")?;
+        }
+        // append metadata to the comment line
+        let xyz = ExtxyzFile::format_molecule(&sub);
+        let (natoms, rest) = xyz.split_once('\n').unwrap_or_default();
+        let (title, atoms) = rest.split_once('\n').unwrap_or_default();
+        writeln!(s, "{natoms}\n{} {meta}", title.trim())?;
+        s.push_str(atoms);
+    }
+    if let Some(dir) = f.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    gut::fs::write_to_file(f, &s)?;
+
+    Ok(())
+}
 // c617a958 ends here
 
 // [[file:../../trajectory.note::2ebc3172][2ebc3172]]
-use super::io::{Reaction, ReactionWriter};
+use super::checkpoint::Checkpoint;
+use super::io::{Reaction, ReactionFormat, ReactionWriter};
+use super::fate::AtomFateTracker;
+use super::lifetime::{InstanceWriter, SpeciesLifetimes};
+use super::population::{get_species_population, PopulationWriter, SpeciesPopulation};
+use crate::reaxff::{ReaxffBondsFrame, ReaxffBondsReader};
 
-pub fn find_chemical_reactions_in_trajectory(trjfile: &Path, options: &ReactionOptions) -> Result<()> {
-    use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
 
-    let noise_event_life = options.noise_event_life;
+pub fn find_chemical_reactions_in_trajectory(trjfile: &Path, options: &ReactionOptions) -> Result<()> {
+    // NOTE: no context frames required if noise removing is disabled
+    let noise_event_life = options.noise_removing().unwrap_or(0);
     let chunk_size = options.chunk_size;
     ensure!(
         chunk_size > 2 * noise_event_life + 1,
         "invalid chunk_size/noise_event_life parameters"
     );
 
-    let overlap_size = noise_event_life;
+    // NOTE: only frames after the left `noise_event_life` frames and
+    // before the right `noise_event_life` frames are analyzed in each
+    // chunk, so the next chunk should start early enough to cover the
+    // frame transitions that follow the last analyzed one.
+    let overlap_size = 2 * noise_event_life + 1;
+    // the number of frames for analyzing multiple chunks at once
+    let nparallel = options.parallel_chunks;
+    ensure!(nparallel > 0, "invalid parallel_chunks option!");
+    let batch_size = chunk_size + (nparallel - 1) * (chunk_size - overlap_size);
 
     let step_by = options.read_trajectory_step_by;
     ensure!(step_by != 0, "invalid read_trajectory_step_by option!");
     if step_by > 1 {
         println!("Reading trajectory stepping by {step_by} at each frame.");
     }
-    // Set lattice from extxyz title
-    let mut mols = gchemol::io::read(trjfile)?.step_by(step_by).map(|mut mol| {
-        if options.read_lattice_extxyz {
-            if let Some(lat) = gchemol::io::formats::ExtxyzFile::read_lattice(&mol.title()) {
-                debug!("Set lattice from extxyz title");
-                mol.set_lattice(lat);
-            }
-        }
-        mol
-    });
-    // write reactions in parquet format
-    let pqfile = trjfile.with_file_name("reaction.pq");
-    let mut writer = ReactionWriter::new(&pqfile)?;
+    let mols = read_trajectory(trjfile, options)?.step_by(step_by);
+
+    // write reactions in parquet format by default
+    let reaction_file = options.reaction_file.clone().unwrap_or_else(|| trjfile.with_file_name("reaction.pq"));
+    let reaction_format = options.reaction_format.unwrap_or_else(|| ReactionFormat::from_path(&reaction_file));
+    // NOTE: other outputs are written in the same dir as reaction file,
+    // as the trajectory could be in a read-only dir
+    let output_file = |name: &str| reaction_file.with_file_name(name);
+
+    // the states for resuming
+    let ckpt_file = output_file("reaction-checkpoint.json");
+    let mut state = if options.resume {
+        println!("Resuming from checkpoint {}", ckpt_file.display());
+        Checkpoint::load(&ckpt_file)?
+    } else {
+        Checkpoint::default()
+    };
+    if state.volume.is_none() {
+        state.volume = options.volume;
+    }
+    if state.fates.is_empty() {
+        state.fates = AtomFateTracker::new(&options.tracked_atoms);
+    }
+
+    // NOTE: the output written after the checkpoint will be discarded
+    // when resuming
+    let mut writer = if options.resume {
+        ReactionWriter::resume(&reaction_file, reaction_format, state.reaction_file_position)?
+    } else {
+        ReactionWriter::with_format(&reaction_file, reaction_format)?
+    };
+    let mut population_writer = match options.species_population_file.as_deref() {
+        Some(f) if options.resume => PopulationWriter::resume(f, state.population_file_position)?.into(),
+        Some(f) => PopulationWriter::new(f)?.into(),
+        None => None,
+    };
+    let mut instance_writer = match options.species_lifetimes_file.as_deref().map(InstanceWriter::instances_file) {
+        Some(f) if options.resume => InstanceWriter::resume(&f, state.instances_file_position)?.into(),
+        Some(f) => InstanceWriter::new(&f)?.into(),
+        None => None,
+    };
 
     let mut reaction_species_dir = None;
     let mut reactive_frames_dir = None;
-    let mut window = VecDeque::new();
-    let mut ichunk = 0;
+    let mut reaxff_bonds = match options.reaxff_bonds_file.as_deref() {
+        Some(f) => ReaxffBondsReader::new(f)?.into(),
+        None => None,
+    };
+    let mut hysteresis = match options.bond_hysteresis {
+        Some([form_scale, break_scale]) => HysteresisBonding::new(form_scale, break_scale)?.into(),
+        None => None,
+    };
+    ensure!(
+        reaxff_bonds.is_none() || hysteresis.is_none(),
+        "ReaxFF bonds cannot be used with hysteresis bonding"
+    );
+    if let (Some(hysteresis), Some(mol)) = (hysteresis.as_mut(), state.window.back()) {
+        hysteresis.reset(mol);
+    }
     if options.write_reaction_species {
-        if let Some(p) = trjfile.parent() {
-            reaction_species_dir = Some(p.join("reaction-species"));
-            reactive_frames_dir = Some(p.join("reactive-frames"));
-        }
+        reaction_species_dir = Some(output_file("reaction-species"));
+        reactive_frames_dir = Some(output_file("reactive-frames"));
     }
-    for (i, mut mol) in mols.enumerate() {
+
+    // fingerprints of reaction species written before
+    let mut species_written = HashSet::new();
+    let mut write_chunk_results = |state: &mut Checkpoint, results: ChunkResults| -> Result<()> {
+        state.ichunk += 1;
+        let ichunk = state.ichunk;
+        // NOTE: write species files here instead of in parallel chunks,
+        // so that each species is written only once
+        if let Some(dir) = reaction_species_dir.as_deref() {
+            let species = results.species.iter().filter(|(fp, _)| species_written.insert(fp.to_string()));
+            super::write_reaction_species(dir, species)?;
+        }
+        let reactions = results.reactions;
+        if !reactions.is_empty() {
+            let n = reactions.len();
+            let m = reactions.iter().filter(|r| r.low_confidence).count();
+            println!("Found {n} reactions in chunk {ichunk} ({m} in low confidence)");
+            writer.write_reactions(&reactions)?;
+            if options.write_reaction_network {
+                reactions.iter().for_each(|r| state.network.add_reaction(r));
+            }
+            if options.reaction_summary_file.is_some() {
+                reactions.iter().for_each(|r| state.summary.add_reaction(r));
+            }
+            if options.timestep.is_some() {
+                reactions.iter().for_each(|r| state.kinetics.add_reaction(r));
+            }
+        }
+        if let Some(w) = population_writer.as_mut() {
+            w.write_population(&results.population)?;
+        }
+        if options.timestep.is_some() {
+            state.kinetics.add_population(&results.population);
+        }
+        state.fates.merge(results.fates);
+        state.lifetimes.merge(results.lifetimes);
+        if let Some(w) = instance_writer.as_mut() {
+            let instances = state.lifetimes.take_destroyed();
+            w.write_instances(instances.iter().map(|(x, censored)| (x, *censored)))?;
+        }
+        // the positions in output files for resuming
+        if options.write_checkpoint {
+            state.reaction_file_position = writer.position()?;
+            if let Some(w) = population_writer.as_mut() {
+                state.population_file_position = w.position()?;
+            }
+            if let Some(w) = instance_writer.as_mut() {
+                state.instances_file_position = w.position()?;
+            }
+        }
+        Ok(())
+    };
+
+    for (i, mol) in mols.enumerate() {
+        let mut mol = mol?;
+        if i < state.nframes {
+            // skip frames read before, and keep ReaxFF bonds frames in
+            // sync
+            if let Some(reader) = reaxff_bonds.as_mut() {
+                let timestep = super::io::read_timestep_from_title(&mol.title());
+                reader.next_frame(timestep, step_by)?;
+            }
+            continue;
+        }
+        if let Some(reader) = reaxff_bonds.as_mut() {
+            let timestep = super::io::read_timestep_from_title(&mol.title());
+            let frame = reader.next_frame(timestep, step_by)?;
+            set_bonds_from_reaxff(&mut mol, &frame, options.reaxff_bond_order_cutoff)?;
+        } else if let Some(hysteresis) = hysteresis.as_mut() {
+            // NOTE: bonds depend on previous frame, so we create them in
+            // reading order
+            hysteresis.rebond(&mut mol);
+        }
+        // NOTE: read MD step, time and other properties before title
+        // overwritten
+        let frame_time = FrameTime::from_title(i * step_by, &mol.title(), options.timestep);
+        mol.properties.store("Frame time", frame_time)?;
+        if let (Some(_), Some(time)) = (options.timestep, frame_time.time) {
+            state.kinetics.add_frame_time(time);
+        }
+        let frame_properties = super::io::read_frame_properties(&mol.title());
+        if let Some(energy) = super::io::get_frame_energy(&frame_properties) {
+            mol.properties.store("energy", energy)?;
+        }
+        mol.properties.store("Frame properties", frame_properties)?;
         mol.set_title(format!("{i}"));
-        window.push_back(mol);
-        if window.len() == chunk_size {
-            ichunk += 1;
-            // Process the current window
-            // Create one contiguous slice of `Molecule`
-            println!("Processing chunk {ichunk}");
-            let reactions = process_mol_chunk(
-                window.make_contiguous(),
+        if state.volume.is_none() {
+            state.volume = mol.lattice.map(|lat| lat.volume());
+        }
+        state.window.push_back(mol);
+        state.nframes = i + 1;
+        if state.window.len() == batch_size {
+            let all_results = process_full_chunks(
+                &mut state.window,
                 &options,
-                reaction_species_dir.as_deref(),
+                overlap_size,
+                state.ichunk,
                 reactive_frames_dir.as_deref(),
             )?;
-            if !reactions.is_empty() {
-                let n = reactions.len();
-                println!("Found {n} reactions in chunk {ichunk}");
-                writer.write_reactions(&reactions);
+            // write results in frame order
+            for results in all_results {
+                write_chunk_results(&mut state, results)?;
             }
-            // Prepare for the next window: keep the last `overlap_size` elements
-            while window.len() > overlap_size {
-                window.pop_front();
+            if options.write_checkpoint {
+                state.save(&ckpt_file)?;
             }
         }
     }
+    // the remaining full chunks
+    let all_results = process_full_chunks(
+        &mut state.window,
+        &options,
+        overlap_size,
+        state.ichunk,
+        reactive_frames_dir.as_deref(),
+    )?;
+    for results in all_results {
+        write_chunk_results(&mut state, results)?;
+    }
 
-    // NOTE: ignore the last chunk
-    if !window.is_empty() {
-        println!("The last chunk was discarded for context reason.");
+    // NOTE: the trailing chunk has not enough frames on the right side,
+    // so we process it with reduced context. The frame transitions in
+    // the right context of previous chunk have not been analyzed, which
+    // start right after the left context of the trailing chunk.
+    let window = &mut state.window;
+    if window.len() > noise_event_life + 1 {
+        println!("Processing trailing chunk {} with reduced context", state.ichunk + 1);
+        let results = process_mol_chunk(
+            window.make_contiguous(),
+            &options,
+            0,
+            state.ichunk == 0,
+            reactive_frames_dir.as_deref(),
+        )?;
+        write_chunk_results(&mut state, results)?;
+    } else if !window.is_empty() {
+        println!("The last {} frames were not analyzed for context reason.", window.len());
     }
 
     // close the writer for parquet
     writer.close()?;
+    if let Some(w) = population_writer {
+        w.close()?;
+    }
+
+    if options.write_reaction_network {
+        let prefix = output_file("reaction-network");
+        state.network.write_files(&prefix)?;
+        println!("Reaction network written to {}.{{graphml,dot,json}}", prefix.display());
+    }
+
+    if let Some(f) = options.reaction_summary_file.as_deref() {
+        state.summary.write_file(f, options.summary_sort_by_count)?;
+        println!("Unique reactions written to {}", f.display());
+    }
+
+    if let (Some(f), Some(mut w)) = (options.species_lifetimes_file.as_deref(), instance_writer) {
+        // destroyed instances have been written, and the remaining ones
+        // exist in the last frame
+        w.write_instances(state.lifetimes.instances())?;
+        w.close()?;
+        state.lifetimes.write_files(f)?;
+        println!("Species lifetimes written to {}", f.display());
+    }
+
+    if let Some(f) = options.atom_fates_file.as_deref() {
+        state.fates.write_json(f)?;
+        println!("Species history of tracked atoms written to {}", f.display());
+    }
+
+    if let Some(timestep) = options.timestep {
+        let f = output_file("reaction-kinetics.csv");
+        // the time interval between two analyzed frames, from the frame
+        // times in titles or MD steps
+        let dt = state.kinetics.frame_interval().unwrap_or(timestep * step_by as f64);
+        state.kinetics.write_rate_constants(&f, dt, state.volume)?;
+        println!("Reaction rate constants written to {}", f.display());
+    }
 
     Ok(())
 }
 
+/// Return true if `trjfile` is in LAMMPS dump format.
+fn is_lammps_dump(trjfile: &Path) -> bool {
+    trjfile.extension().map_or(false, |x| x == "dump" || x == "lammpstrj")
+}
+
+/// Read molecules from trajectory file in xyz/extxyz format or in
+/// LAMMPS dump format.
+fn read_trajectory<'a>(
+    trjfile: &'a Path,
+    options: &ReactionOptions,
+) -> Result<Box<dyn Iterator<Item = Result<Molecule>> + 'a>> {
+    if is_lammps_dump(trjfile) {
+        let elements = options.lammps_type_elements.clone();
+        ensure!(
+            !elements.is_empty(),
+            "element symbols for atom types are required for LAMMPS dump file"
+        );
+        let frames = crate::lammps::parse_lammps_dump_file(trjfile)?;
+        Ok(Box::new(frames.map(move |frame| frame.to_molecule(&elements))))
+    } else {
+        // Set lattice from extxyz title
+        let read_lattice = options.read_lattice_extxyz;
+        let mols = gchemol::io::read(trjfile)?.map(move |mut mol| {
+            if read_lattice {
+                if let Some(lat) = gchemol::io::formats::ExtxyzFile::read_lattice(&mol.title()) {
+                    debug!("Set lattice from extxyz title");
+                    mol.set_lattice(lat);
+                }
+            }
+            Ok(mol)
+        });
+        Ok(Box::new(mols))
+    }
+}
+
+/// Replace bonds in `mol` with bonds in ReaxFF bonds `frame` with bond
+/// order no less than `cutoff`.
+fn set_bonds_from_reaxff(mol: &mut Molecule, frame: &ReaxffBondsFrame, cutoff: f64) -> Result<()> {
+    use gchemol::Bond;
+
+    mol.unbound();
+    for [u, v] in frame.bonds_above(cutoff) {
+        ensure!(
+            mol.has_atom(u) && mol.has_atom(v),
+            "invalid atom pair {u}-{v} in ReaxFF bonds of timestep {}",
+            frame.timestep
+        );
+        mol.add_bond(u, v, Bond::default());
+    }
+    Ok(())
+}
+
+/// Reactions, species population, atom fates and species lifetimes
+/// found in one chunk
+#[derive(Debug, Default)]
+struct ChunkResults {
+    reactions: Vec<Reaction>,
+    // reactants and products to be written
+    species: ReactionSpecies,
+    population: Vec<SpeciesPopulation>,
+    fates: AtomFateTracker,
+    lifetimes: SpeciesLifetimes,
+}
+
 fn get_chemical_reactions(
     mols: &[Molecule],
-    noise_event_life: usize,
-    // root dir for writing reaction species
-    reaction_species_dir: Option<&Path>,
+    options: &ReactionOptions,
+    right_context: usize,
+    // the chunk starts from the first frame in trajectory
+    first_chunk: bool,
     reactive_frames_dir: Option<&Path>,
-) -> Result<Vec<Reaction>> {
+) -> Result<ChunkResults> {
     // NOTE: this is bugging
     // let mut mols = get_active_molecules(&mols)?;
     let mut mols = mols.to_vec();
+    // no noise removing if None
+    let noise_event_life = options.noise_removing();
     let mut states = remove_inactive_bonding_pairs(&mols);
     let keys: Vec<_> = states.bonding_pairs().collect();
     for &[u, v] in &keys {
         info!("{u:03}-{v:03}: {}", states.bonding_events_code([u, v]));
     }
-    info!("When noise events removed:");
-    let bonds_to_repair = remove_noise_bonding_events(&mut states, noise_event_life);
-    let keys: Vec<_> = states.bonding_pairs().collect();
-    for &[u, v] in &keys {
-        info!("{u:03}={v:03}: {}", states.bonding_events_code([u, v]));
+    if let Some(noise_event_life) = noise_event_life {
+        info!("When noise events removed:");
+        let bonds_to_repair = remove_noise_bonding_events(&mut states, noise_event_life);
+        let keys: Vec<_> = states.bonding_pairs().collect();
+        for &[u, v] in &keys {
+            info!("{u:03}={v:03}: {}", states.bonding_events_code([u, v]));
+        }
+        repair_bonding_states(&mut mols, &bonds_to_repair);
     }
-    repair_bonding_states(&mut mols, &bonds_to_repair);
+    let noise_event_life = noise_event_life.unwrap_or(0);
+
+    let mut species = ReactionSpecies::new();
+    let reactions = find_reactions(
+        &mols,
+        &states,
+        noise_event_life,
+        right_context,
+        options,
+        options.write_reaction_species.then_some(&mut species),
+        reactive_frames_dir,
+    )?;
+
+    // NOTE: count frames entered by the analyzed frame transitions, so
+    // that each frame is counted only once in overlapping chunks. The
+    // leading frames in the first chunk are never entered, and they are
+    // counted with their denoised bonding states.
+    let n = mols.len();
+    let first = if first_chunk { 0 } else { noise_event_life + 1 };
+    let analyzed = &mols[first..n - right_context];
+    // count species population in frames with repaired bonds
+    let population = if options.species_population_file.is_some() || options.timestep.is_some() {
+        get_species_population(analyzed)
+    } else {
+        vec![]
+    };
+    let mut fates = AtomFateTracker::new(&options.tracked_atoms);
+    let mut lifetimes = SpeciesLifetimes::default();
+    for mol in analyzed {
+        let frame = mol.title().parse()?;
+        if !fates.is_empty() {
+            fates.add_frame(frame, mol);
+        }
+        if options.species_lifetimes_file.is_some() {
+            lifetimes.add_frame(frame, mol);
+        }
+    }
+
+    Ok(ChunkResults { reactions, species, population, fates, lifetimes })
+}
+
+/// Create bonds for molecules in parallel.
+fn rebond_molecules(mols: &mut [Molecule], options: &ReactionOptions) {
+    // bonds have been created when reading ReaxFF bonds file or using
+    // hysteresis bonding
+    let bonded = options.reaxff_bonds_file.is_some() || options.bond_hysteresis.is_some();
 
-    find_reactions(&mols, &states, noise_event_life, reaction_species_dir, reactive_frames_dir)
+    mols.into_par_iter().for_each(|mol| {
+        // ignore molecules already `rebond` in overlap region
+        if mol.nbonds() == 0 && !bonded {
+            options.bonding.rebond(mol);
+        }
+    });
 }
 
-/// Create bonds and find chemical reactions in this chunk
+/// Create bonds and find chemical reactions in this chunk. Only frames
+/// with at least `right_context` frames on the right side are analyzed.
 fn process_mol_chunk(
     chunk: &mut [Molecule],
     options: &ReactionOptions,
-    reaction_species_dir: Option<&Path>,
+    right_context: usize,
+    first_chunk: bool,
     reactive_frames_dir: Option<&Path>,
-) -> Result<Vec<Reaction>> {
-    let noise_event_life = options.noise_event_life;
+) -> Result<ChunkResults> {
+    rebond_molecules(chunk, options);
 
-    chunk.into_par_iter().for_each(|mut mol| {
-        // ignore molecules already `rebond` in overlap region
-        if mol.nbonds() == 0 {
-            mol.rebond();
-        }
-    });
+    get_chemical_reactions(chunk, options, right_context, first_chunk, reactive_frames_dir)
+}
 
-    get_chemical_reactions(chunk, noise_event_life, reaction_species_dir, reactive_frames_dir)
+/// Find chemical reactions in all full chunks in `window` in parallel.
+/// Two neighboring chunks share `overlap_size` frames, the same as in
+/// sequential processing. Frames not required for the next chunk will
+/// be removed from `window`. The results are returned in chunk order.
+fn process_full_chunks(
+    window: &mut VecDeque<Molecule>,
+    options: &ReactionOptions,
+    overlap_size: usize,
+    // the number of chunks processed before
+    ichunk: usize,
+    reactive_frames_dir: Option<&Path>,
+) -> Result<Vec<ChunkResults>> {
+    let chunk_size = options.chunk_size;
+    if window.len() < chunk_size {
+        return Ok(vec![]);
+    }
+    let stride = chunk_size - overlap_size;
+    let nchunks = (window.len() - overlap_size) / stride;
+    println!("Processing chunk {}-{}", ichunk + 1, ichunk + nchunks);
+
+    // Create one contiguous slice of `Molecule`
+    let mols = window.make_contiguous();
+    rebond_molecules(mols, options);
+    let mols = &*mols;
+    let noise_event_life = options.noise_removing().unwrap_or(0);
+    let results = (0..nchunks)
+        .into_par_iter()
+        .map(|i| {
+            let chunk = &mols[i * stride..i * stride + chunk_size];
+            get_chemical_reactions(
+                chunk,
+                options,
+                noise_event_life,
+                ichunk + i == 0,
+                reactive_frames_dir,
+            )
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // Prepare for the next chunk: keep the last `overlap_size` frames
+    // in the last chunk
+    window.drain(..nchunks * stride);
+    Ok(results)
 }
 // 2ebc3172 ends here
 
@@ -265,3 +719,208 @@ fn test_reaction_algo() -> Result<()> {
     Ok(())
 }
 // dd2f60bb ends here
+
+// [[file:../../trajectory.note::7e2d9c41][7e2d9c41]]
+#[test]
+fn test_reactions_across_chunks() -> Result<()> {
+    let mols: Vec<_> = gchemol::io::read("tests/files/lty.xyz")?.take(80).collect();
+
+    // global frames of reactive frames found in the first `nframes`
+    // frames of trajectory analyzed in chunks of `chunk_size` frames
+    let reactive_frames = |nframes: usize, chunk_size: usize| -> Result<Vec<usize>> {
+        let tdir = gut::fs::tempfile::tempdir()?;
+        let trjfile = tdir.path().join("traj.xyz");
+        gchemol::io::write(&trjfile, &mols[..nframes])?;
+        let options = ReactionOptions {
+            noise_event_life: 5,
+            chunk_size,
+            write_reaction_species: true,
+            ..Default::default()
+        };
+        find_chemical_reactions_in_trajectory(&trjfile, &options)?;
+        let mut frames = vec![];
+        let dir = tdir.path().join("reactive-frames");
+        if dir.exists() {
+            for entry in std::fs::read_dir(dir)? {
+                let path = entry?.path();
+                if path.extension().map_or(false, |x| x == "mol2") {
+                    frames.push(path.file_stem().unwrap().to_string_lossy().parse()?);
+                }
+            }
+        }
+        frames.sort();
+        Ok(frames)
+    };
+
+    // all frames analyzed in one chunk
+    let frames = reactive_frames(80, 100)?;
+    assert!(!frames.is_empty());
+    // reactions between two chunks should not be lost
+    assert_eq!(reactive_frames(80, 30)?, frames);
+
+    // the trajectory ends with the last full chunk (30 + 19 frames), and
+    // the reaction in frame 47 is in the right context of the chunk
+    let frames = reactive_frames(49, 100)?;
+    assert!(frames.contains(&47));
+    assert_eq!(reactive_frames(49, 30)?, frames);
+
+    Ok(())
+}
+// 7e2d9c41 ends here
+
+// [[file:../../trajectory.note::5d1f8e36][5d1f8e36]]
+#[test]
+fn test_resume_from_checkpoint() -> Result<()> {
+    let tdir = gut::fs::tempfile::tempdir()?;
+    // all outputs are written in the dir of reaction file
+    let trjdir = tdir.path().join("data");
+    std::fs::create_dir(&trjdir)?;
+    let trjfile = trjdir.join("traj.xyz");
+    let mols: Vec<_> = gchemol::io::read("tests/files/lty.xyz")?.take(80).collect();
+    gchemol::io::write(&trjfile, &mols)?;
+
+    let outputs = [
+        "reaction.csv",
+        "population.csv",
+        "summary.csv",
+        "lifetimes.csv",
+        "lifetimes.instances.csv",
+        "lifetimes.distribution.csv",
+        "reaction-kinetics.csv",
+    ];
+    let mut options = ReactionOptions {
+        noise_event_life: 5,
+        chunk_size: 30,
+        timestep: Some(1.0),
+        write_checkpoint: true,
+        write_reaction_network: true,
+        write_reaction_species: true,
+        reaction_file: tdir.path().join(outputs[0]).into(),
+        species_population_file: tdir.path().join(outputs[1]).into(),
+        reaction_summary_file: tdir.path().join(outputs[2]).into(),
+        species_lifetimes_file: tdir.path().join(outputs[3]).into(),
+        ..Default::default()
+    };
+    find_chemical_reactions_in_trajectory(&trjfile, &options)?;
+    let expected: Vec<_> = outputs.iter().map(|f| gut::fs::read_file(tdir.path().join(f))).try_collect()?;
+
+    // resume from the last checkpoint, and the output written after
+    // that will be written again
+    let ckpt = Checkpoint::load(&tdir.path().join("reaction-checkpoint.json"))?;
+    assert!(ckpt.nframes < 80);
+    assert!(ckpt.reaction_file_position < expected[0].len() as u64);
+    options.resume = true;
+    find_chemical_reactions_in_trajectory(&trjfile, &options)?;
+    for (f, expected) in outputs.iter().zip(expected) {
+        assert_eq!(gut::fs::read_file(tdir.path().join(f))?, expected, "{f}");
+    }
+    assert!(tdir.path().join("reaction-network.json").exists());
+    assert!(tdir.path().join("reaction-species").is_dir());
+    assert_eq!(std::fs::read_dir(&trjdir)?.count(), 1);
+
+    // rows in Parquet files are rewritten when resuming
+    let reaction_file = tdir.path().join("reaction.pq");
+    let population_file = tdir.path().join("population.pq");
+    options.reaction_file = reaction_file.clone().into();
+    options.species_population_file = population_file.clone().into();
+    options.resume = false;
+    find_chemical_reactions_in_trajectory(&trjfile, &options)?;
+    let read_rows = || -> Result<_> {
+        let reactions = serde_json::to_string(&super::io::read_reactions(&reaction_file)?)?;
+        let population: Vec<SpeciesPopulation> = super::io::read_parquet_rows(&population_file)?;
+        Ok((reactions, serde_json::to_string(&population)?))
+    };
+    let expected = read_rows()?;
+    let ckpt = Checkpoint::load(&tdir.path().join("reaction-checkpoint.json"))?;
+    assert!(ckpt.reaction_file_position > 0);
+    assert!(ckpt.reaction_file_position < super::io::read_reactions(&reaction_file)?.len() as u64);
+    assert!(ckpt.population_file_position > 0);
+    options.resume = true;
+    find_chemical_reactions_in_trajectory(&trjfile, &options)?;
+    assert_eq!(read_rows()?, expected);
+
+    Ok(())
+}
+// 5d1f8e36 ends here
+
+// [[file:../../trajectory.note::3c8e71f4][3c8e71f4]]
+#[test]
+fn test_parallel_chunks() -> Result<()> {
+    let f = "tests/files/lty.xyz";
+    let options = ReactionOptions {
+        noise_event_life: 5,
+        chunk_size: 30,
+        timestep: Some(1.0),
+        ..Default::default()
+    };
+    let overlap_size = 11;
+    let mut mols: Vec<_> = gchemol::io::read(f)?.take(80).collect();
+    for (i, mol) in mols.iter_mut().enumerate() {
+        mol.set_title(format!("{i}"));
+    }
+
+    // sequential processing
+    let mut expected = vec![];
+    let stride = options.chunk_size - overlap_size;
+    let mut i = 0;
+    while i + options.chunk_size <= mols.len() {
+        let mut chunk = mols[i..i + options.chunk_size].to_vec();
+        expected.push(process_mol_chunk(&mut chunk, &options, 5, i == 0, None)?);
+        i += stride;
+    }
+
+    let mut window: VecDeque<_> = mols.into_iter().collect();
+    let results = process_full_chunks(&mut window, &options, overlap_size, 0, None)?;
+    assert_eq!(results.len(), expected.len());
+    assert_eq!(window.len(), 80 - expected.len() * stride);
+    let summary = |x: &ChunkResults| {
+        let reactions = x.reactions.iter().map(|r| (&r.global_frame, &r.reactants, &r.products)).collect_vec();
+        let population = x.population.iter().map(|p| (&p.global_frame, &p.species, p.count)).collect_vec();
+        format!("{reactions:?}{population:?}")
+    };
+    for (x, y) in results.iter().zip(expected.iter()) {
+        assert_eq!(summary(x), summary(y));
+    }
+
+    // all frames before the trailing chunk are counted exactly once,
+    // including the leading frames in the first chunk
+    let frames = results
+        .iter()
+        .flat_map(|x| x.population.iter().map(|p| (p.global_frame.parse::<usize>().unwrap(), &p.species)))
+        .collect_vec();
+    assert_eq!(frames.iter().unique().count(), frames.len());
+    let frames = frames.into_iter().map(|(i, _)| i).dedup().collect_vec();
+    assert_eq!(frames, (0..80 - window.len() + 6).collect_vec());
+
+    Ok(())
+}
+// 3c8e71f4 ends here
+
+// [[file:../../trajectory.note::9d4e1b76][9d4e1b76]]
+#[test]
+fn test_set_bonds_from_reaxff() -> Result<()> {
+    use gchemol::{Atom, Bond};
+
+    let atoms = [[0.0, 0.0, 0.0], [0.0, 0.0, 0.7], [0.0, 0.0, 1.4]].map(|p| Atom::new("H", p));
+    let mut mol = Molecule::from_atoms(atoms);
+    mol.add_bond(2, 3, Bond::default());
+    let frame = ReaxffBondsFrame {
+        timestep: 10,
+        bonds: vec![([1, 2], 0.9), ([2, 3], 0.2)],
+    };
+    // bonds guessed before are replaced
+    set_bonds_from_reaxff(&mut mol, &frame, 0.3)?;
+    assert_eq!(mol.bonds().map(|(u, v, _)| [u.min(v), u.max(v)]).collect_vec(), [[1, 2]]);
+    set_bonds_from_reaxff(&mut mol, &frame, 0.1)?;
+    assert_eq!(mol.nbonds(), 2);
+
+    // atom ids not in trajectory frame
+    let frame = ReaxffBondsFrame {
+        timestep: 20,
+        bonds: vec![([1, 4], 0.9)],
+    };
+    assert!(set_bonds_from_reaxff(&mut mol, &frame, 0.3).is_err());
+
+    Ok(())
+}
+// 9d4e1b76 ends here
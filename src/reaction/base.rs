@@ -6,14 +6,82 @@ use std::collections::BTreeMap;
 // 74e5e10a ends here
 
 // [[file:../../trajectory.note::eb6afa69][eb6afa69]]
+/// Bonding states of one atom pair in consecutive frames, packed in bits.
+/// Bits not set are for unbonded states.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct PackedStates {
+    bits: Vec<u64>,
+}
+
+impl PackedStates {
+    fn get(&self, i: usize) -> bool {
+        self.bits.get(i / 64).map_or(false, |w| w >> (i % 64) & 1 == 1)
+    }
+
+    fn set(&mut self, i: usize, state: bool) {
+        let k = i / 64;
+        if k >= self.bits.len() {
+            self.bits.resize(k + 1, 0);
+        }
+        if state {
+            self.bits[k] |= 1 << (i % 64);
+        } else {
+            self.bits[k] &= !(1 << (i % 64));
+        }
+    }
+
+    /// Invert states in frame range `start..end`.
+    fn flip_range(&mut self, start: usize, end: usize) {
+        if start >= end {
+            return;
+        }
+        let (k1, k2) = (start / 64, (end - 1) / 64);
+        if k2 >= self.bits.len() {
+            self.bits.resize(k2 + 1, 0);
+        }
+        for k in k1..=k2 {
+            // the mask of bits in range for word k
+            let lo = if k == k1 { start % 64 } else { 0 };
+            let hi = if k == k2 { (end - 1) % 64 } else { 63 };
+            self.bits[k] ^= (u64::MAX >> (63 - hi)) & (u64::MAX << lo);
+        }
+    }
+
+    /// The number of bonded states.
+    fn count_bonded(&self) -> usize {
+        self.bits.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// Return frame indices `i` in `0..nframes-1` with state changed
+    /// from frame `i` to `i+1`, in ascending order.
+    fn find_changes(&self, nframes: usize) -> Vec<usize> {
+        let n = nframes.saturating_sub(1);
+        let mut changes = vec![];
+        for k in 0..self.bits.len().min((n + 63) / 64) {
+            let next = self.bits.get(k + 1).copied().unwrap_or(0);
+            let w = self.bits[k];
+            // bit i set for different states of frame i and i+1
+            let mut x = w ^ (w >> 1 | next << 63);
+            if (k + 1) * 64 > n {
+                x &= u64::MAX >> ((k + 1) * 64 - n);
+            }
+            while x != 0 {
+                changes.push(k * 64 + x.trailing_zeros() as usize);
+                x &= x - 1;
+            }
+        }
+        changes
+    }
+}
+
 /// Bonding states (bonded or unbonded) for each pair of atoms in each frame
 #[derive(Debug, Default, Clone)]
 pub struct BondingStates {
     // the total number of frames with bonding states
     nframes: usize,
     // key: [u, v] as atom pair u--v
-    // value: a map with key for frame index, and value for bonding state
-    inner: BTreeMap<[usize; 2], BTreeMap<usize, bool>>,
+    // value: bonding states of all frames
+    inner: BTreeMap<[usize; 2], PackedStates>,
 }
 
 // for bond: u-v == v-u
@@ -26,8 +94,7 @@ fn ordered(key: [usize; 2]) -> [usize; 2] {
 impl BondingStates {
     /// Insert bonding state of frame `frame_index` for bond `key` between atom `u` and `v`
     pub fn set_frame(&mut self, frame_index: usize, key: [usize; 2], state: bool) {
-        let mut entry = self.inner.entry(ordered(key)).or_default();
-        entry.insert(frame_index, state);
+        self.inner.entry(ordered(key)).or_default().set(frame_index, state);
 
         // NOTE: record the largest frame index for the number of frames
         if frame_index + 1 > self.nframes {
@@ -38,11 +105,7 @@ impl BondingStates {
     /// Get bonding state of frame `frame_index` for bond `key` between atom `u` and `v`
     pub fn get_frame(&self, frame_index: usize, key: [usize; 2]) -> bool {
         // if no such bonding pair, we should return false
-        if let Some(states) = self.inner.get(&ordered(key)) {
-            states.get(&frame_index).copied().unwrap_or_default()
-        } else {
-            false
-        }
+        self.inner.get(&ordered(key)).map_or(false, |states| states.get(frame_index))
     }
 
     /// The number of bonding pairs.
@@ -61,28 +124,19 @@ impl BondingStates {
 impl BondingStates {
     /// Return bonding states code for pair u--v
     pub fn bonding_states_code(&self, key: [usize; 2]) -> impl Iterator<Item = bool> + '_ {
-        (0..self.nframes).map(move |iframe| self.get_frame(iframe, key))
+        let states = self.inner.get(&ordered(key));
+        (0..self.nframes).map(move |iframe| states.map_or(false, |x| x.get(iframe)))
     }
 
     /// Return human readable bonding events code for pair of atom `key` u--v
     pub fn bonding_events_code(&self, key: [usize; 2]) -> String {
-        let frames: Vec<_> = (0..self.nframes).collect();
-
-        frames
-            .windows(2)
-            .map(|pair| {
-                let iframe1 = pair[0];
-                let iframe2 = pair[1];
-                let state1 = self.get_frame(iframe1, key);
-                let state2 = self.get_frame(iframe2, key);
-                match (state1, state2) {
-                    (false, true) => "↑",
-                    (true, false) => "↓",
-                    (true, true) => "-",
-                    (false, false) => "-",
-                }
-            })
-            .collect()
+        let mut code = vec!["-"; self.nframes.saturating_sub(1)];
+        if let Some(states) = self.inner.get(&ordered(key)) {
+            for i in states.find_changes(self.nframes) {
+                code[i] = if states.get(i) { "↓" } else { "↑" };
+            }
+        }
+        code.concat()
     }
 }
 // 45ddc634 ends here
@@ -92,21 +146,14 @@ impl BondingStates {
     /// Remove bonding pairs no bond breaking or forming
     /// changes. Returns the number of removed bonding pairs.
     pub fn remove_inactive_bonding_pairs(&mut self) -> usize {
-        let keys: Vec<_> = self.bonding_pairs().collect();
-
-        // find bonding pairs that have no changes
-        let inactive_keys1 = keys.iter().filter(|&key| self.bonding_states_code(*key).all(|bonded| bonded));
-        let inactive_keys2 = keys
-            .iter()
-            .filter(|&key| self.bonding_states_code(*key).all(|bonded| !bonded));
-
-        // remove these bonding pairs
-        let mut n_removed = 0;
-        for key in inactive_keys1.chain(inactive_keys2).collect_vec() {
-            let _ = self.inner.remove(key);
-            n_removed += 1;
-        }
-        n_removed
+        let n = self.inner.len();
+        // remove bonding pairs bonded or unbonded in all frames
+        let nframes = self.nframes;
+        self.inner.retain(|_, states| {
+            let nbonded = states.count_bonded();
+            nbonded > 0 && nbonded < nframes
+        });
+        n - self.inner.len()
     }
 }
 // a4eba9be ends here
@@ -122,33 +169,40 @@ impl BondingStates {
     /// These states in begin and end sides with `noise_event_life` items are
     /// not affected by the removing.
     pub fn remove_noise_events(&mut self, key: [usize; 2], noise_event_life: usize) -> Vec<usize> {
-        let mut states: Vec<_> = (0..self.nframes).map(|iframe| self.get_frame(iframe, key)).collect();
-        let positions_inverted = fix_noise_states(&mut states, noise_event_life);
-        for &iframe in positions_inverted.iter() {
-            let state = !self.get_frame(iframe, key);
-            self.set_frame(iframe, key, state);
+        let nframes = self.nframes;
+        let states = match self.inner.get_mut(&ordered(key)) {
+            Some(states) => states,
+            None => return vec![],
+        };
+        let changes = states.find_changes(nframes);
+        let noise_ranges = find_noise_ranges(&changes, states.get(0), noise_event_life);
+        for (start, end) in noise_ranges.iter().copied() {
+            states.flip_range(start, end);
         }
-        positions_inverted
+        noise_ranges.into_iter().flat_map(|(start, end)| start..end).collect_vec()
     }
 
     /// Find reactive bonds in valid frame region in context of
     /// `noise_event_life`. Panics if `noise_event_life` too large to
     /// fit the whole frame region.
     pub fn find_reactive_bonds(&self, noise_event_life: usize) -> Vec<[usize; 2]> {
-        let frames: Vec<_> = (0..self.nframes).collect();
+        self.find_reactive_bonds_with_context(noise_event_life, noise_event_life)
+    }
 
-        let istart = noise_event_life;
-        let iend = self.nframes - noise_event_life;
+    /// Find reactive bonds in frame region excluding `left` context
+    /// frames at the begin side and `right` context frames at the end
+    /// side. Panics if the context is too large to fit the whole frame
+    /// region.
+    pub fn find_reactive_bonds_with_context(&self, left: usize, right: usize) -> Vec<[usize; 2]> {
+        let istart = left;
+        let iend = self.nframes.saturating_sub(right);
         assert!(istart < iend, "invalid istart..iend {istart}..{iend}");
 
         let mut reactive_bonds = HashSet::new();
-        for &bond in self.inner.keys() {
-            let states: Vec<_> = self.bonding_states_code(bond).collect();
-            let changes = find_reactive_changes(&states[istart..iend]);
-            if !changes.is_empty() {
-                for [u, v] in changes {
-                    // fix index shift issue
-                    reactive_bonds.insert([u + istart, v + istart]);
+        for states in self.inner.values() {
+            for i in states.find_changes(iend) {
+                if i >= istart {
+                    reactive_bonds.insert([i, i + 1]);
                 }
             }
         }
@@ -156,7 +210,34 @@ impl BondingStates {
     }
 }
 
+/// Find frame ranges of noise events from frame indices in `changes`
+/// where bonding state changes, with `first_state` for the state in the
+/// first frame. A noise event is a bond breaking followed by forming (or
+/// forming followed by breaking) within `noise_event_life` frames. The
+/// same as in `fix_noise_states`, the kind of noise events matches more
+/// will be selected.
+fn find_noise_ranges(changes: &[usize], first_state: bool, noise_event_life: usize) -> Vec<(usize, usize)> {
+    let mut ranges = [vec![], vec![]];
+    for (k, pair) in changes.windows(2).enumerate() {
+        // states flip alternately at each change
+        let bonded = first_state ^ (k % 2 == 1);
+        if pair[1] - pair[0] - 1 <= noise_event_life {
+            let i = if bonded { 0 } else { 1 };
+            ranges[i].push((pair[0] + 1, pair[1] + 1));
+        }
+    }
+    let [breaking_first, forming_first] = ranges;
+    if breaking_first.len() < forming_first.len() {
+        forming_first
+    } else {
+        breaking_first
+    }
+}
+
+// NOTE: the string based implementation for removing noise events, as
+// the reference for `BondingStates` in tests
 /// Find start and end positions for noise event pattern (in pair) in `code`
+#[cfg(test)]
 fn find_noise_codes(code: &str, n_space: usize) -> Vec<(usize, usize)> {
     use regex::Regex;
 
@@ -185,6 +266,7 @@ fn find_noise_codes(code: &str, n_space: usize) -> Vec<(usize, usize)> {
     }
 }
 
+#[cfg(test)]
 fn states_to_events(states: &[bool]) -> String {
     let nframes = states.len();
     let frames: Vec<_> = (0..nframes).collect();
@@ -207,6 +289,7 @@ fn states_to_events(states: &[bool]) -> String {
         .collect()
 }
 
+#[cfg(test)]
 pub(self) fn fix_noise_states(states: &mut [bool], noise_event_life: usize) -> Vec<usize> {
     let ecode = states_to_events(states);
     let matched_positions = find_noise_codes(&ecode, noise_event_life);
@@ -228,6 +311,7 @@ pub(self) fn fix_noise_states(states: &mut [bool], noise_event_life: usize) -> V
 }
 
 /// Find positions that have chemical reactions
+#[cfg(test)]
 fn find_reactive_changes(states: &[bool]) -> Vec<[usize; 2]> {
     assert!(!states.is_empty());
 
@@ -365,3 +449,68 @@ fn test_bonding_states() -> Result<()> {
     Ok(())
 }
 // ace163f1 ends here
+
+// [[file:../../trajectory.note::5c1e9a07][5c1e9a07]]
+#[test]
+fn test_reactive_bonds_with_context() {
+    let mut states = BondingStates::default();
+    for iframe in 0..10 {
+        states.set_frame(iframe, [1, 0], iframe >= 8);
+    }
+    assert!(states.find_reactive_bonds(2).is_empty());
+    let bonds = states.find_reactive_bonds_with_context(2, 0);
+    assert_eq!(bonds, vec![[7, 8]]);
+}
+// 5c1e9a07 ends here
+
+// [[file:../../trajectory.note::c85f2a3d][c85f2a3d]]
+#[test]
+fn test_packed_bonding_states() {
+    // pseudo random states with noise events, crossing word boundaries
+    let mut seed = 7_u64;
+    let mut random_states = |n: usize| {
+        let mut state = false;
+        (0..n)
+            .map(|_| {
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                if seed >> 60 == 0 {
+                    state = !state;
+                }
+                state
+            })
+            .collect_vec()
+    };
+
+    for nframes in [1, 2, 63, 64, 65, 150, 300] {
+        let states = random_states(nframes);
+        let mut packed = BondingStates::default();
+        for (i, &state) in states.iter().enumerate() {
+            packed.set_frame(i, [2, 1], state);
+        }
+        assert_eq!(packed.bonding_states_code([1, 2]).collect_vec(), states);
+        let code: String = states_to_events(&states).replace('F', "↑").replace('B', "↓");
+        assert_eq!(packed.bonding_events_code([1, 2]), code);
+        let mut reactive_bonds = find_reactive_changes(&states);
+        reactive_bonds.sort();
+        assert_eq!(packed.find_reactive_bonds_with_context(0, 0), reactive_bonds);
+
+        for noise_event_life in [0, 1, 5, 50] {
+            let mut fixed = states.clone();
+            let positions = fix_noise_states(&mut fixed, noise_event_life);
+            let mut packed = packed.clone();
+            assert_eq!(packed.remove_noise_events([1, 2], noise_event_life), positions);
+            assert_eq!(packed.bonding_states_code([1, 2]).collect_vec(), fixed);
+        }
+    }
+
+    // bonded or unbonded in all frames
+    let mut states = BondingStates::default();
+    for i in 0..100 {
+        states.set_frame(i, [1, 2], true);
+        states.set_frame(i, [1, 3], i == 70);
+        states.set_frame(i, [1, 4], false);
+    }
+    assert_eq!(states.remove_inactive_bonding_pairs(), 2);
+    assert_eq!(states.bonding_pairs().collect_vec(), [[1, 3]]);
+}
+// c85f2a3d ends here
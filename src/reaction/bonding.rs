@@ -0,0 +1,306 @@
+// [[file:../../trajectory.note::7b2e94c1][7b2e94c1]]
+use crate::common::*;
+
+use gchemol::{Bond, Molecule};
+use std::collections::BTreeMap;
+// 7b2e94c1 ends here
+
+// [[file:../../trajectory.note::d8f3a620][d8f3a620]]
+/// Distance cutoffs for each pair of elements, which can be loaded
+/// from a TOML file like below:
+///
+/// ```toml
+/// # for element pairs not listed (optional)
+/// default = 1.6
+///
+/// [cutoffs]
+/// C-H = 1.3
+/// C-C = 1.8
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DistanceCutoffs {
+    /// The distance cutoff for element pairs not listed in `cutoffs`.
+    /// If not set, these pairs will never be bonded.
+    pub default: Option<f64>,
+    /// Distance cutoffs in Å for element pairs, such as "C-H"
+    pub cutoffs: BTreeMap<String, f64>,
+}
+
+// for element pair: C-H == H-C
+fn element_pair_key(sym1: &str, sym2: &str) -> String {
+    if sym1 <= sym2 {
+        format!("{sym1}-{sym2}")
+    } else {
+        format!("{sym2}-{sym1}")
+    }
+}
+
+impl DistanceCutoffs {
+    /// Load distance cutoffs from TOML file `f`.
+    pub fn from_toml_file(f: &Path) -> Result<Self> {
+        let s = gut::fs::read_file(f)?;
+        let mut table: Self = toml_from_str(&s).with_context(|| format!("invalid cutoff table in {f:?}"))?;
+        // normalize keys for element pairs in reversed order
+        table.cutoffs = table
+            .cutoffs
+            .into_iter()
+            .map(|(k, v)| match k.split_once('-') {
+                Some((s1, s2)) => Ok((element_pair_key(s1.trim(), s2.trim()), v)),
+                None => bail!("invalid element pair: {k:?}"),
+            })
+            .collect::<Result<_>>()?;
+        Ok(table)
+    }
+
+    /// Return distance cutoff for element pair `sym1` and `sym2`.
+    pub fn get(&self, sym1: &str, sym2: &str) -> Option<f64> {
+        self.cutoffs.get(&element_pair_key(sym1, sym2)).copied().or(self.default)
+    }
+
+    fn max_cutoff(&self) -> f64 {
+        self.cutoffs.values().copied().chain(self.default).fold(0.0, f64::max)
+    }
+}
+
+fn toml_from_str(s: &str) -> Result<DistanceCutoffs> {
+    use gut::config::Configure;
+    DistanceCutoffs::from_toml(s)
+}
+// d8f3a620 ends here
+
+// [[file:../../trajectory.note::2a61c7e8][2a61c7e8]]
+/// The criterion for determining chemical bonds between atoms.
+#[derive(Debug, Clone, Default)]
+pub enum BondingCriterion {
+    /// The default bonding algorithm in gchemol (`Molecule::rebond`).
+    #[default]
+    Default,
+    /// Bonded if distance <= (r1 + r2) * scale, where r1 and r2 are
+    /// covalent radii.
+    CovalentRadius(f64),
+    /// Bonded if distance <= the cutoff for element pair.
+    DistanceCutoff(DistanceCutoffs),
+    /// Bonded if bond order >= threshold. The bond order is estimated
+    /// using Pauling's relation: exp((r1 + r2 - distance) / 0.3)
+    BondOrder(f64),
+}
+
+impl FromStr for BondingCriterion {
+    type Err = Error;
+
+    /// Parse bonding criterion from string such as "default",
+    /// "scale:1.15", "cutoff:cutoffs.toml" or "bond-order:0.3".
+    fn from_str(s: &str) -> Result<Self> {
+        let (kind, value) = s.split_once(':').unwrap_or((s, ""));
+        let criterion = match kind.trim() {
+            "default" => Self::Default,
+            "scale" => Self::CovalentRadius(value.trim().parse().context("invalid scale factor")?),
+            "cutoff" => Self::DistanceCutoff(DistanceCutoffs::from_toml_file(value.trim().as_ref())?),
+            "bond-order" => Self::BondOrder(value.trim().parse().context("invalid bond order threshold")?),
+            _ => bail!("invalid bonding criterion: {s:?}"),
+        };
+        Ok(criterion)
+    }
+}
+
+// Pauling's bond order: exp((r0 - r)/0.3)
+const PAULING_BOND_ORDER_B: f64 = 0.3;
+
+fn covalent_radii(mol: &Molecule) -> HashMap<usize, f64> {
+    mol.atoms().filter_map(|(i, a)| Some((i, a.get_cov_radius()?))).collect()
+}
+
+/// Return atom pairs `i`-`j` (i < j) with distance no larger than
+/// `rcut`. For periodic system, the distance is calculated in minimum
+/// image convention.
+fn find_neighbors(mol: &Molecule, rcut: f64) -> Vec<(usize, usize, f64)> {
+    let nh = mol.create_neighbor_probe();
+    let mut pairs = vec![];
+    for i in mol.numbers() {
+        // the nearest image for each neighbor
+        let mut neighbors: BTreeMap<usize, f64> = BTreeMap::new();
+        for n in nh.neighbors(i, rcut).filter(|n| n.node > i) {
+            let d = neighbors.entry(n.node).or_insert(n.distance);
+            *d = d.min(n.distance);
+        }
+        pairs.extend(neighbors.into_iter().map(|(j, d)| (i, j, d)));
+    }
+    pairs
+}
+
+fn set_bonds(mol: &mut Molecule, bonds: impl IntoIterator<Item = [usize; 2]>) {
+    mol.unbound();
+    for [u, v] in bonds {
+        mol.add_bond(u, v, Bond::default());
+    }
+}
+
+impl BondingCriterion {
+    /// Return the largest distance that could be bonded between atoms
+    /// in `mol`.
+    fn max_bond_distance(&self, radii: &HashMap<usize, f64>) -> f64 {
+        let rmax = radii.values().copied().fold(0.0, f64::max);
+        match self {
+            Self::Default => unreachable!(),
+            Self::CovalentRadius(scale) => 2.0 * rmax * scale,
+            Self::DistanceCutoff(table) => table.max_cutoff(),
+            Self::BondOrder(threshold) => 2.0 * rmax - PAULING_BOND_ORDER_B * threshold.ln(),
+        }
+    }
+
+    /// Return true if atom `i` and `j` in distance `d` is bonded.
+    fn is_bonded(&self, mol: &Molecule, radii: &HashMap<usize, f64>, i: usize, j: usize, d: f64) -> bool {
+        let r0 = || Some(radii.get(&i)? + radii.get(&j)?);
+        match self {
+            Self::Default => unreachable!(),
+            Self::CovalentRadius(scale) => r0().map_or(false, |r0| d <= r0 * scale),
+            Self::DistanceCutoff(table) => {
+                let sym_i = mol.get_atom_unchecked(i).symbol();
+                let sym_j = mol.get_atom_unchecked(j).symbol();
+                table.get(sym_i, sym_j).map_or(false, |rcut| d <= rcut)
+            }
+            Self::BondOrder(threshold) => {
+                r0().map_or(false, |r0| ((r0 - d) / PAULING_BOND_ORDER_B).exp() >= *threshold)
+            }
+        }
+    }
+
+    /// Find bonded atom pairs in `mol`. For periodic system, the
+    /// bonds are determined in minimum image convention.
+    pub fn find_bonds(&self, mol: &Molecule) -> Vec<[usize; 2]> {
+        if let Self::Default = self {
+            let mut mol = mol.clone();
+            mol.rebond();
+            return mol.bonds().map(|(u, v, _)| [u, v]).collect();
+        }
+
+        let radii = covalent_radii(mol);
+        let rcut = self.max_bond_distance(&radii);
+        find_neighbors(mol, rcut)
+            .into_iter()
+            .filter_map(|(i, j, d)| self.is_bonded(mol, &radii, i, j, d).then_some([i, j]))
+            .collect()
+    }
+
+    /// Recalculates all bonds in `mol` using this criterion.
+    pub fn rebond(&self, mol: &mut Molecule) {
+        if let Self::Default = self {
+            mol.rebond();
+        } else {
+            let bonds = self.find_bonds(mol);
+            set_bonds(mol, bonds);
+        }
+    }
+}
+// 2a61c7e8 ends here
+
+// [[file:../../trajectory.note::81c4f5ad][81c4f5ad]]
+/// Dual-cutoff bonding with hysteresis: a bond forms when the distance
+/// drops below r_form, and breaks only when the distance rises above
+/// r_break. Both cutoffs are the sum of covalent radii scaled by a
+/// factor. The bonding states are stable by construction, and can be
+/// determined frame by frame in one pass without noise removing.
+#[derive(Debug, Clone)]
+pub struct HysteresisBonding {
+    form_scale: f64,
+    break_scale: f64,
+    // bonded atom pairs in previous frame
+    bonded: std::collections::HashSet<[usize; 2]>,
+}
+
+impl HysteresisBonding {
+    /// Construct with scale factors of covalent radii for r_form and
+    /// r_break.
+    pub fn new(form_scale: f64, break_scale: f64) -> Result<Self> {
+        ensure!(
+            0.0 < form_scale && form_scale <= break_scale,
+            "invalid hysteresis scale factors: {form_scale}, {break_scale}"
+        );
+        Ok(Self {
+            form_scale,
+            break_scale,
+            bonded: Default::default(),
+        })
+    }
+
+    /// Recalculates all bonds in `mol` in context of bonds in previous
+    /// frame. Frames should be processed in trajectory order.
+    pub fn rebond(&mut self, mol: &mut Molecule) {
+        let radii = covalent_radii(mol);
+        let rmax = radii.values().copied().fold(0.0, f64::max);
+        let mut bonded = std::collections::HashSet::new();
+        for (i, j, d) in find_neighbors(mol, 2.0 * rmax * self.break_scale) {
+            if let (Some(ri), Some(rj)) = (radii.get(&i), radii.get(&j)) {
+                let r0 = ri + rj;
+                if d < r0 * self.form_scale || (d <= r0 * self.break_scale && self.bonded.contains(&[i, j])) {
+                    bonded.insert([i, j]);
+                }
+            }
+        }
+        set_bonds(mol, bonded.iter().copied());
+        self.bonded = bonded;
+    }
+
+    /// Reset bonding states using bonds in `mol`, which should be the
+    /// previous frame for next call of `rebond`.
+    pub fn reset(&mut self, mol: &Molecule) {
+        self.bonded = mol.bonds().map(|(u, v, _)| [u.min(v), u.max(v)]).collect();
+    }
+}
+// 81c4f5ad ends here
+
+// [[file:../../trajectory.note::e5c17d39][e5c17d39]]
+#[test]
+fn test_bonding_criterion() -> Result<()> {
+    use gchemol::Atom;
+
+    // H2 molecule in a long distance
+    let atom1 = Atom::new("H", [0.0, 0.0, 0.0]);
+    let atom2 = Atom::new("H", [0.0, 0.0, 1.0]);
+    let mol = Molecule::from_atoms(vec![atom1, atom2]);
+
+    let criterion: BondingCriterion = "scale:1.0".parse()?;
+    assert!(criterion.find_bonds(&mol).is_empty());
+    let criterion: BondingCriterion = "scale:2.0".parse()?;
+    assert_eq!(criterion.find_bonds(&mol), vec![[1, 2]]);
+
+    let criterion: BondingCriterion = "bond-order:0.1".parse()?;
+    assert_eq!(criterion.find_bonds(&mol), vec![[1, 2]]);
+    let criterion: BondingCriterion = "bond-order:0.9".parse()?;
+    assert!(criterion.find_bonds(&mol).is_empty());
+
+    let table: DistanceCutoffs = toml_from_str("[cutoffs]\nH-H = 1.2")?;
+    assert_eq!(table.get("H", "H"), Some(1.2));
+    assert_eq!(table.get("C", "H"), None);
+    let mut mol = mol;
+    BondingCriterion::DistanceCutoff(table).rebond(&mut mol);
+    assert_eq!(mol.nbonds(), 1);
+
+    assert!("foo:1".parse::<BondingCriterion>().is_err());
+
+    Ok(())
+}
+// e5c17d39 ends here
+
+// [[file:../../trajectory.note::f2a97c08][f2a97c08]]
+#[test]
+fn test_hysteresis_bonding() -> Result<()> {
+    use gchemol::Atom;
+
+    let h2 = |d: f64| Molecule::from_atoms(vec![Atom::new("H", [0.0, 0.0, 0.0]), Atom::new("H", [0.0, 0.0, d])]);
+    let r0 = 2.0 * Atom::new("H", [0.0; 3]).get_cov_radius().unwrap();
+
+    let mut hysteresis = HysteresisBonding::new(1.1, 1.5)?;
+    let mut nbonds = vec![];
+    // far => in between => close => in between => far
+    for d in [1.6, 1.3, 1.0, 1.3, 1.6] {
+        let mut mol = h2(d * r0);
+        hysteresis.rebond(&mut mol);
+        nbonds.push(mol.nbonds());
+    }
+    assert_eq!(nbonds, vec![0, 0, 1, 1, 0]);
+    assert!(HysteresisBonding::new(1.5, 1.1).is_err());
+
+    Ok(())
+}
+// f2a97c08 ends here
@@ -0,0 +1,95 @@
+// [[file:../../trajectory.note::b91d4c27][b91d4c27]]
+use super::fate::AtomFateTracker;
+use super::kinetics::ReactionKinetics;
+use super::lifetime::SpeciesLifetimes;
+use super::network::ReactionNetwork;
+use super::summary::ReactionSummary;
+use crate::common::*;
+
+use gchemol::Molecule;
+use std::collections::VecDeque;
+// b91d4c27 ends here
+
+// [[file:../../trajectory.note::5ac0e3d8][5ac0e3d8]]
+/// The states of reaction analysis required for resuming an interrupted
+/// run. Reactions and species population written into files are not
+/// saved, but the positions for appending to these files.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// The number of trajectory frames read (after stepping)
+    pub nframes: usize,
+    /// The number of chunks processed
+    pub ichunk: usize,
+    /// The overlap frames with bonds for the next chunk
+    pub window: VecDeque<Molecule>,
+    /// The position for appending reactions to reaction file
+    pub reaction_file_position: u64,
+    /// The position for appending to species population file
+    pub population_file_position: u64,
+    /// The position for appending to species instances file
+    pub instances_file_position: u64,
+    /// Reaction events and species population for estimating rate
+    /// constants
+    pub kinetics: ReactionKinetics,
+    /// Reaction network of processed chunks
+    pub network: ReactionNetwork,
+    /// Unique reactions of processed chunks
+    pub summary: ReactionSummary,
+    /// The volume of simulation box
+    pub volume: Option<f64>,
+    /// Species history of tracked atoms
+    pub fates: AtomFateTracker,
+    /// Fragment instances alive and lifetime statistics
+    pub lifetimes: SpeciesLifetimes,
+}
+
+impl Checkpoint {
+    /// Load checkpoint from file `f` in JSON format.
+    pub fn load(f: &Path) -> Result<Self> {
+        let s = gut::fs::read_file(f)?;
+        let checkpoint = serde_json::from_str(&s).with_context(|| format!("invalid checkpoint file: {f:?}"))?;
+        Ok(checkpoint)
+    }
+
+    /// Save checkpoint into file `f` in JSON format. The old file will be
+    /// replaced only when writing completed.
+    pub fn save(&self, f: &Path) -> Result<()> {
+        let tmp = f.with_extension("tmp");
+        let s = serde_json::to_string(self)?;
+        gut::fs::write_to_file(&tmp, &s)?;
+        std::fs::rename(&tmp, f)?;
+        Ok(())
+    }
+}
+// 5ac0e3d8 ends here
+
+// [[file:../../trajectory.note::e80f3a6b][e80f3a6b]]
+#[test]
+fn test_checkpoint() -> Result<()> {
+    let f = "tests/files/lty.xyz";
+    let mut checkpoint = Checkpoint::default();
+    for mut mol in gchemol::io::read(f)?.take(2) {
+        mol.rebond();
+        checkpoint.window.push_back(mol);
+    }
+    checkpoint.nframes = 2;
+    checkpoint.reaction_file_position = 100;
+    let reaction = super::io::test_reaction(1, &["h2"], &["h", "h"]);
+    checkpoint.kinetics.add_reaction(&reaction);
+    checkpoint.network.add_reaction(&reaction);
+    checkpoint.summary.add_reaction(&reaction);
+
+    let tdir = gut::fs::tempfile::tempdir()?;
+    let ckpt = tdir.path().join("checkpoint.json");
+    checkpoint.save(&ckpt)?;
+    let loaded = Checkpoint::load(&ckpt)?;
+    assert_eq!(loaded.nframes, 2);
+    assert_eq!(loaded.reaction_file_position, 100);
+    assert_eq!(loaded.kinetics.rate_constants(1.0, None)[0].count, 1);
+    assert_eq!(loaded.network.reactions().count(), 1);
+    assert_eq!(loaded.summary.unique_reactions(false)[0].first_frame, Some(1));
+    assert_eq!(loaded.window[1].nbonds(), checkpoint.window[1].nbonds());
+
+    Ok(())
+}
+// e80f3a6b ends here
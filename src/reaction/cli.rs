@@ -1,4 +1,6 @@
 // [[file:../../trajectory.note::d74a391a][d74a391a]]
+use super::bonding::BondingCriterion;
+use super::io::ReactionFormat;
 use super::options::ReactionOptions;
 use crate::common::*;
 
@@ -10,14 +12,20 @@ use super::*;
 use gut::cli::*;
 use gut::config::*;
 
-/// Analysis of reactive trajectory in xyz/extxyz format.
+/// Analysis of reactive trajectory in xyz/extxyz or LAMMPS dump format.
 #[derive(Debug, Parser)]
+#[command(subcommand_negates_reqs = true, args_conflicts_with_subcommands = true)]
 pub struct ReactionCli {
-    /// The trajectory file in xyz format.
-    trjfile: PathBuf,
+    /// The trajectory file in xyz format, or in LAMMPS dump format if
+    /// file extension is `dump` or `lammpstrj`.
+    #[clap(required = true)]
+    trjfile: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<ReactionCommand>,
 
     /// Write reaction species (if enabled, these files can be found
-    /// in the same dir as trajectory file. Check directories
+    /// in the same dir as reaction file. Check directories
     /// `reaction-species` and `reactive-frames`).
     #[clap(short = 'w')]
     write_reaction_species: bool,
@@ -42,6 +50,179 @@ pub struct ReactionCli {
     /// Do not read lattice data from xyz title in extxyz format.
     #[clap(long = "ignore-lattice")]
     ignore_lattice_extxyz: bool,
+
+    /// Write reaction network built from found reactions (check files
+    /// `reaction-network.{graphml,dot,json}` in the same dir as
+    /// reaction file).
+    #[clap(long = "network")]
+    write_reaction_network: bool,
+
+    /// Write species population of each frame into this file, in CSV
+    /// format if file extension is csv, otherwise in Parquet format.
+    #[clap(long = "population")]
+    species_population_file: Option<PathBuf>,
+
+    /// Write found reactions into this file instead of `reaction.pq` in
+    /// the same dir as trajectory file. Other output files without
+    /// paths given are written in the same dir as this file, which is
+    /// required for trajectory in a read-only dir.
+    #[clap(short = 'o', long = "output")]
+    reaction_file: Option<PathBuf>,
+
+    /// The format for writing reactions. If not set, the format is
+    /// guessed from file extension: `csv` for CSV, `jsonl` for JSON
+    /// Lines, `sqlite` or `db` for SQLite, otherwise Parquet.
+    #[clap(long = "format", value_enum)]
+    reaction_format: Option<ReactionFormat>,
+
+    /// The MD timestep in fs. If set, rate constants of reactions will
+    /// be estimated (check `reaction-kinetics.csv` in the same dir as
+    /// reaction file), with the time between frames read from frame
+    /// titles. If no time found in frame titles, the time of frames is
+    /// computed as timestep * MD step (such as "Timestep: 100" in
+    /// LAMMPS dump), or as timestep * frame if no MD step found either.
+    #[clap(long = "timestep")]
+    timestep: Option<f64>,
+
+    /// The volume of simulation box in Å^3 for estimating second-order
+    /// rate constants. If not set, the lattice volume will be used.
+    #[clap(long = "volume")]
+    volume: Option<f64>,
+
+    /// The criterion for determining chemical bonds. Available values:
+    /// "default" for gchemol default, "scale:1.15" for covalent radius
+    /// scale factor, "cutoff:cutoffs.toml" for distance cutoff table of
+    /// element pairs, "bond-order:0.3" for Pauling bond order
+    /// threshold.
+    #[clap(long = "bonding", default_value = "default")]
+    bonding: BondingCriterion,
+
+    /// Read bonds from ReaxFF bonds file written by LAMMPS `fix
+    /// reaxff/bonds` instead of guessing from geometry. The frames are
+    /// matched by timestep in xyz title (such as "Timestep: 100"), or
+    /// by order if not found. The atom ids should be the same as the
+    /// atom serial numbers in trajectory.
+    #[clap(long = "reaxff-bonds")]
+    reaxff_bonds_file: Option<PathBuf>,
+
+    /// The bond order cutoff for bonds in ReaxFF bonds file.
+    #[clap(long = "bo-cutoff", default_value = "0.3")]
+    reaxff_bond_order_cutoff: f64,
+
+    /// Use hysteresis bonding instead of noise removing: a bond forms
+    /// when the distance drops below r_form, and breaks only when the
+    /// distance rises above r_break. The two values are scale factors
+    /// of covalent radii for r_form and r_break, such as "1.1,1.3".
+    #[clap(long = "hysteresis", value_delimiter = ',', num_args = 2)]
+    bond_hysteresis: Option<Vec<f64>>,
+
+    /// Element symbols for atom types in LAMMPS dump file, such as
+    /// "C,H,O" for type 1 as C, type 2 as H, and type 3 as O. The box
+    /// bounds in dump file will be used as lattice if fully periodic.
+    #[clap(long = "types", value_delimiter = ',')]
+    lammps_type_elements: Vec<String>,
+
+    /// The number of chunks to be analyzed at once in parallel. A
+    /// larger value requires more memory for holding frames.
+    #[clap(long = "parallel-chunks", default_value = "1")]
+    parallel_chunks: usize,
+
+    /// Write checkpoint periodically (check file
+    /// `reaction-checkpoint.json` in the same dir as reaction file).
+    #[clap(long = "checkpoint")]
+    write_checkpoint: bool,
+
+    /// Resume the analysis from the checkpoint written before. New
+    /// reactions and species population will be appended to files
+    /// written before, and new checkpoints will be written. Files in
+    /// Parquet format are rewritten with the rows before the checkpoint,
+    /// which requires them closed properly in the interrupted run.
+    #[clap(long = "resume")]
+    resume: bool,
+
+    /// Write unique reactions with counts into this file, in CSV format
+    /// if file extension is csv, in Markdown format if md, otherwise in
+    /// Parquet format.
+    #[clap(long = "summary")]
+    reaction_summary_file: Option<PathBuf>,
+
+    /// Sort unique reactions in summary by count in descending order.
+    #[clap(long = "sort-by-count", requires = "reaction_summary_file")]
+    summary_sort_by_count: bool,
+
+    /// Track which species the selected atoms belong to in each frame,
+    /// such as "1,5,9" for atoms 1, 5 and 9.
+    #[clap(long = "track-atoms", value_delimiter = ',', requires = "atom_fates_file")]
+    tracked_atoms: Vec<usize>,
+
+    /// Write species history of tracked atoms into this file in JSON
+    /// format.
+    #[clap(long = "fates", requires = "tracked_atoms")]
+    atom_fates_file: Option<PathBuf>,
+
+    /// Write mean lifetimes of species into this CSV file. Fragments
+    /// are identified by their atoms from formation to destruction.
+    /// The lifetime distribution and all fragment instances will be
+    /// written into "*.distribution.csv" and "*.instances.csv" files.
+    #[clap(long = "lifetimes")]
+    species_lifetimes_file: Option<PathBuf>,
+
+    /// Only record reactions involving any of these elements in
+    /// reactants or products, such as "O,N".
+    #[clap(long = "elements", value_delimiter = ',')]
+    filter_elements: Vec<String>,
+
+    /// Only record reactions involving any of these atoms in reactants
+    /// or products, such as "1-10,15".
+    #[clap(long = "atoms")]
+    filter_atoms: Option<String>,
+
+    /// Only record reactions with any reactant or product in these
+    /// chemical formulas, such as "H2O,HO".
+    #[clap(long = "formulas", value_delimiter = ',')]
+    filter_formulas: Vec<String>,
+
+    /// Write K frames before and after each reaction event into
+    /// `reactive-frames` directory in extxyz format, cropped to
+    /// reacting fragments and their neighbors. The frames are limited
+    /// by the context frames in the chunk.
+    #[clap(long = "window", value_name = "K", requires = "write_reaction_species")]
+    reaction_window: Option<usize>,
+
+    /// The radius in Å around reacting atoms for cropping frames in
+    /// reaction window.
+    #[clap(long = "window-shell", default_value = "4.0", requires = "reaction_window")]
+    reaction_window_shell: f64,
+
+    /// Center reacting atoms in periodic cell when writing reactive
+    /// frames, with molecules kept whole across the cell boundary.
+    #[clap(long = "center", requires = "write_reaction_species")]
+    center_reactive_frames: bool,
+}
+
+#[derive(Debug, Subcommand)]
+enum ReactionCommand {
+    /// Summarize reactions in `reaction.pq` written in reaction analysis.
+    Summarize(SummarizeCli),
+}
+
+#[derive(Debug, Args)]
+struct SummarizeCli {
+    /// The reaction file in Parquet format written in reaction analysis.
+    pqfile: PathBuf,
+
+    /// The number of top reactions and species in report.
+    #[clap(short = 'n', long = "top", default_value = "10")]
+    top: usize,
+
+    /// The number of time bins for counting reactions.
+    #[clap(long = "bins", default_value = "10")]
+    bins: usize,
+
+    /// Write the report in JSON format into this file. The default is
+    /// `reaction-report.json` in the same dir as reaction.pq file.
+    #[clap(long = "json")]
+    json_file: Option<PathBuf>,
 }
 
 impl ReactionCli {
@@ -49,7 +230,10 @@ impl ReactionCli {
         let args = Self::parse();
         args.verbose.setup_logger();
 
-        process(&args)?;
+        match &args.command {
+            Some(ReactionCommand::Summarize(cmd)) => summarize(cmd)?,
+            None => process(&args)?,
+        }
 
         Ok(())
     }
@@ -59,16 +243,68 @@ impl ReactionCli {
 // [[file:../../trajectory.note::093b2b9c][093b2b9c]]
 fn process(cli: &ReactionCli) -> Result<()> {
     use crate::reaction::algo::find_chemical_reactions_in_trajectory;
+    use crate::reaction::filter::ReactionFilter;
+
+    let reaction_filter = ReactionFilter {
+        elements: cli.filter_elements.clone(),
+        atoms: match cli.filter_atoms.as_deref() {
+            Some(s) => gut::utils::parse_numbers_human_readable(s)?,
+            None => vec![],
+        },
+        formulas: cli.filter_formulas.clone(),
+    };
     let options = ReactionOptions {
         read_trajectory_step_by: cli.step_size,
         noise_event_life: cli.noise_event_life,
         write_reaction_species: cli.write_reaction_species,
         chunk_size: cli.chunk_size,
         read_lattice_extxyz: !cli.ignore_lattice_extxyz,
+        write_reaction_network: cli.write_reaction_network,
+        species_population_file: cli.species_population_file.clone(),
+        reaction_file: cli.reaction_file.clone(),
+        reaction_format: cli.reaction_format,
+        timestep: cli.timestep,
+        volume: cli.volume,
+        bonding: cli.bonding.clone(),
+        reaxff_bonds_file: cli.reaxff_bonds_file.clone(),
+        reaxff_bond_order_cutoff: cli.reaxff_bond_order_cutoff,
+        bond_hysteresis: cli.bond_hysteresis.as_ref().map(|x| [x[0], x[1]]),
+        lammps_type_elements: cli.lammps_type_elements.clone(),
+        parallel_chunks: cli.parallel_chunks,
+        write_checkpoint: cli.write_checkpoint || cli.resume,
+        resume: cli.resume,
+        reaction_summary_file: cli.reaction_summary_file.clone(),
+        summary_sort_by_count: cli.summary_sort_by_count,
+        tracked_atoms: cli.tracked_atoms.clone(),
+        atom_fates_file: cli.atom_fates_file.clone(),
+        species_lifetimes_file: cli.species_lifetimes_file.clone(),
+        reaction_filter,
+        reaction_window: cli.reaction_window,
+        reaction_window_shell: cli.reaction_window_shell,
+        center_reactive_frames: cli.center_reactive_frames,
         ..Default::default()
     };
 
-    find_chemical_reactions_in_trajectory(&cli.trjfile, &options)?;
+    let trjfile = cli.trjfile.as_deref().ok_or(format_err!("trajectory file is required"))?;
+    find_chemical_reactions_in_trajectory(trjfile, &options)?;
+
+    Ok(())
+}
+
+fn summarize(cmd: &SummarizeCli) -> Result<()> {
+    use crate::reaction::io::read_reactions;
+    use crate::reaction::report::ReactionReport;
+
+    let reactions = read_reactions(&cmd.pqfile)?;
+    let report = ReactionReport::new(&reactions, cmd.top, cmd.bins);
+    print!("{}", report.to_text());
+
+    let f = match cmd.json_file.as_ref() {
+        Some(f) => f.to_owned(),
+        None => cmd.pqfile.with_file_name("reaction-report.json"),
+    };
+    report.write_json(&f)?;
+    println!("\nReport written to {}", f.display());
 
     Ok(())
 }
@@ -0,0 +1,146 @@
+// [[file:../../trajectory.note::5d2f8e61][5d2f8e61]]
+use crate::common::*;
+
+use gchemol::Molecule;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+// 5d2f8e61 ends here
+
+// [[file:../../trajectory.note::b3a07c94][b3a07c94]]
+/// The species containing one atom in consecutive frames.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpeciesInterval {
+    /// The first global frame in this species
+    pub first_frame: usize,
+    /// The last global frame in this species
+    pub last_frame: usize,
+    pub formula: String,
+    /// Canonical identifier of the species
+    pub canonical_id: String,
+    /// Atoms in the fragment of this species
+    pub fragment: Vec<usize>,
+    /// Bonds in the fragment of this species
+    pub bonds: Vec<[usize; 2]>,
+}
+
+/// Track the species history of selected atoms across trajectory frames.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AtomFateTracker {
+    /// The species history of each tracked atom, in frame order.
+    pub fates: BTreeMap<usize, Vec<SpeciesInterval>>,
+}
+
+impl AtomFateTracker {
+    /// Create a tracker for `atoms` (in atom serial numbers).
+    pub fn new(atoms: &[usize]) -> Self {
+        let fates = atoms.iter().map(|&a| (a, vec![])).collect();
+        Self { fates }
+    }
+
+    /// Return true if no atom tracked.
+    pub fn is_empty(&self) -> bool {
+        self.fates.is_empty()
+    }
+
+    /// Update species history of tracked atoms using `mol` in global
+    /// `frame`. Frames should be added in trajectory order.
+    pub fn add_frame(&mut self, frame: usize, mol: &Molecule) {
+        for (&atom, history) in self.fates.iter_mut() {
+            if !mol.has_atom(atom) {
+                continue;
+            }
+            let fragment: BTreeSet<usize> = mol.connected_fragment_atoms(atom).collect();
+            let bonds = mol
+                .bonds()
+                .map(|(u, v, _)| [u.min(v), u.max(v)])
+                .filter(|[u, _]| fragment.contains(u))
+                .sorted()
+                .collect_vec();
+            let atoms = fragment.iter().copied().collect_vec();
+            match history.last_mut() {
+                Some(last) if last.fragment == atoms && last.bonds == bonds => last.last_frame = frame,
+                _ => {
+                    let species = mol.get_sub_molecule(&fragment).unwrap_or_default();
+                    history.push(SpeciesInterval {
+                        first_frame: frame,
+                        last_frame: frame,
+                        formula: species.formula(),
+                        canonical_id: super::species::CanonicalSpecies::new(&species).id,
+                        fragment: atoms,
+                        bonds,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Append species history in `other` recorded for later frames. The
+    /// same species in adjacent intervals will be merged.
+    pub fn merge(&mut self, other: Self) {
+        for (atom, intervals) in other.fates {
+            let history = self.fates.entry(atom).or_default();
+            for x in intervals {
+                match history.last_mut() {
+                    Some(last) if last.fragment == x.fragment && last.bonds == x.bonds => last.last_frame = x.last_frame,
+                    _ => history.push(x),
+                }
+            }
+        }
+    }
+
+    /// Write species history of tracked atoms into file `f` in JSON format.
+    pub fn write_json(&self, f: &Path) -> Result<()> {
+        let s = serde_json::to_string_pretty(&self.fates)?;
+        gut::fs::write_to_file(f, &s)?;
+        Ok(())
+    }
+}
+
+/// Return species history of `atoms` in frames of `mols`. The global
+/// frame is taken from the molecule title, or its index if not a number.
+pub fn track_atom_fates(mols: &[Molecule], atoms: &[usize]) -> BTreeMap<usize, Vec<SpeciesInterval>> {
+    let mut tracker = AtomFateTracker::new(atoms);
+    for (i, mol) in mols.iter().enumerate() {
+        let frame = mol.title().parse().unwrap_or(i);
+        tracker.add_frame(frame, mol);
+    }
+    tracker.fates
+}
+// b3a07c94 ends here
+
+// [[file:../../trajectory.note::e4c91b0a][e4c91b0a]]
+#[test]
+fn test_atom_fates() {
+    use gchemol::{Atom, Bond};
+
+    // H2 + H => H + H2
+    let atoms = [[0.0, 0.0, 0.0], [0.0, 0.0, 0.7], [0.0, 0.0, 1.4]].map(|p| Atom::new("H", p));
+    let mol = Molecule::from_atoms(atoms);
+    let mut mol1 = mol.clone();
+    mol1.add_bond(1, 2, Bond::default());
+    let mut mol2 = mol.clone();
+    mol2.add_bond(2, 3, Bond::default());
+    let mols = vec![mol1.clone(), mol1.clone(), mol2.clone(), mol2.clone(), mol2];
+
+    let fates = track_atom_fates(&mols, &[1, 2]);
+    assert_eq!(fates[&1].len(), 2);
+    assert_eq!(fates[&1][0].formula, "H2");
+    assert_eq!((fates[&1][0].first_frame, fates[&1][0].last_frame), (0, 1));
+    assert_eq!(fates[&1][1].fragment, vec![1]);
+    assert_eq!((fates[&1][1].first_frame, fates[&1][1].last_frame), (2, 4));
+    assert_eq!(fates[&2][1].fragment, vec![2, 3]);
+
+    // tracking in two parts
+    let mut tracker = AtomFateTracker::new(&[1, 2]);
+    let mut tracker2 = AtomFateTracker::new(&[1, 2]);
+    for (i, mol) in mols.iter().enumerate() {
+        if i < 3 {
+            tracker.add_frame(i, mol);
+        } else {
+            tracker2.add_frame(i, mol);
+        }
+    }
+    tracker.merge(tracker2);
+    assert_eq!(tracker.fates, fates);
+}
+// e4c91b0a ends here
@@ -0,0 +1,74 @@
+// [[file:../../trajectory.note::c6e1f3a9][c6e1f3a9]]
+use gchemol::Molecule;
+// c6e1f3a9 ends here
+
+// [[file:../../trajectory.note::0b85d27e][0b85d27e]]
+/// Select reactions by atoms in reactants and products. A reaction is
+/// selected only if it satisfies all criteria set, and empty criteria
+/// select all reactions.
+#[derive(Debug, Clone, Default)]
+pub struct ReactionFilter {
+    /// Select reactions involving any of these elements.
+    pub elements: Vec<String>,
+    /// Select reactions involving any of these atoms (in atom serial
+    /// numbers).
+    pub atoms: Vec<usize>,
+    /// Select reactions with any reactant or product in these chemical
+    /// formulas, such as "H2O".
+    pub formulas: Vec<String>,
+}
+
+impl ReactionFilter {
+    /// Return true if no criterion set.
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty() && self.atoms.is_empty() && self.formulas.is_empty()
+    }
+
+    /// Return true if the reaction from `reactants` to `products` is
+    /// selected.
+    pub fn matches(&self, reactants: &[Molecule], products: &[Molecule]) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+        let species = || reactants.iter().chain(products);
+        let elements_ok = self.elements.is_empty()
+            || species().any(|mol| mol.symbols().any(|x| self.elements.iter().any(|e| e == x)));
+        let atoms_ok = self.atoms.is_empty() || species().any(|mol| mol.numbers().any(|a| self.atoms.contains(&a)));
+        let formulas_ok = self.formulas.is_empty() || species().any(|mol| self.formulas.contains(&mol.formula()));
+        elements_ok && atoms_ok && formulas_ok
+    }
+}
+// 0b85d27e ends here
+
+// [[file:../../trajectory.note::4d7a20c1][4d7a20c1]]
+#[test]
+fn test_reaction_filter() {
+    use crate::common::*;
+    use gchemol::{Atom, Bond};
+
+    // H2 + OH => H + H2O
+    let atoms = [("H", 1.0), ("H", 1.7), ("O", 2.7), ("H", 3.7)].map(|(s, z)| Atom::new(s, [0.0, 0.0, z]));
+    let mol = Molecule::from_atoms(atoms);
+    let mut mol1 = mol.clone();
+    mol1.add_bond(1, 2, Bond::default());
+    mol1.add_bond(3, 4, Bond::default());
+    let mut mol2 = mol.clone();
+    mol2.add_bond(2, 3, Bond::default());
+    mol2.add_bond(3, 4, Bond::default());
+    let reactants = mol1.fragmented().collect_vec();
+    let products = mol2.fragmented().collect_vec();
+
+    let mut filter = ReactionFilter::default();
+    assert!(filter.matches(&reactants, &products));
+    filter.elements = vec!["O".into()];
+    assert!(filter.matches(&reactants, &products));
+    filter.formulas = vec!["H2O".into()];
+    assert!(filter.matches(&reactants, &products));
+    filter.atoms = vec![5, 6];
+    assert!(!filter.matches(&reactants, &products));
+    filter.atoms = vec![1];
+    assert!(filter.matches(&reactants, &products));
+    filter.elements = vec!["C".into()];
+    assert!(!filter.matches(&reactants, &products));
+}
+// 4d7a20c1 ends here
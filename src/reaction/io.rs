@@ -1,16 +1,30 @@
 // [[file:../../trajectory.note::94912fd0][94912fd0]]
 use crate::common::*;
 
-use gosh_dataset::SimpleParquetFileWriter;
+use gut::cli::ValueEnum;
+use serde_json::Value;
 // 94912fd0 ends here
 
 // [[file:../../trajectory.note::6e775d47][6e775d47]]
-#[derive(Debug, Serialize, Clone, Default)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+// NOTE: allow missing columns in files written by older versions
+#[serde(default)]
 pub struct Reaction {
     #[serde(rename = "Local frame")]
     pub local_frame: usize,
     #[serde(rename = "Global frame")]
     pub global_frame: String,
+    /// The frame index in trajectory file counting from 0, regardless
+    /// of reading step
+    #[serde(rename = "Frame")]
+    pub frame: usize,
+    /// The MD step read from trajectory file
+    #[serde(rename = "MD step")]
+    pub md_step: Option<usize>,
+    #[serde(rename = "Time (fs)")]
+    pub time_fs: Option<f64>,
+    #[serde(rename = "Time (ps)")]
+    pub time_ps: Option<f64>,
     #[serde(rename = "Reactants")]
     pub reactants: Vec<Vec<usize>>,
     #[serde(rename = "Products")]
@@ -23,32 +37,636 @@ pub struct Reaction {
     pub reactants_fingerprints: Vec<String>,
     #[serde(rename = "Products fingeprints")]
     pub products_fingerprints: Vec<String>,
+    /// Canonical identifiers of reactants, which are independent of
+    /// atom order and stable across runs
+    #[serde(rename = "Reactants canonical IDs")]
+    pub reactants_canonical_ids: Vec<String>,
+    #[serde(rename = "Products canonical IDs")]
+    pub products_canonical_ids: Vec<String>,
+    /// SMILES-like line notation of reactants in canonical atom order
+    #[serde(rename = "Reactants SMILES")]
+    pub reactants_smiles: Vec<String>,
+    #[serde(rename = "Products SMILES")]
+    pub products_smiles: Vec<String>,
+    #[serde(rename = "Reactants formulas")]
+    pub reactants_formulas: Vec<String>,
+    #[serde(rename = "Products formulas")]
+    pub products_formulas: Vec<String>,
+    /// Bonds formed in this reaction, in pairs of atom serial numbers
+    #[serde(rename = "Forming bonds")]
+    pub forming_bonds: Vec<Vec<usize>>,
+    /// Bonds broken in this reaction, in pairs of atom serial numbers
+    #[serde(rename = "Breaking bonds")]
+    pub breaking_bonds: Vec<Vec<usize>>,
+    /// The atom map from reactants to products. Each item is a triple
+    /// of atom serial number, index in `reactants`, and index in
+    /// `products`.
+    #[serde(rename = "Atom map")]
+    pub atom_map: Vec<Vec<usize>>,
+    /// False if reactants and products do not consist of the same atoms,
+    /// or any of them is not a whole fragment in its frame, which may be
+    /// caused by bonding artifacts.
+    #[serde(rename = "Atom balanced", alias = "Element balanced")]
+    pub atom_balanced: bool,
+    /// The reaction was found with reduced context frames (at the end
+    /// of trajectory), and could be a noise event.
+    #[serde(rename = "Low confidence")]
+    pub low_confidence: bool,
+    /// The energy of the frame before reaction, in the unit of
+    /// trajectory file
+    #[serde(rename = "Energy before")]
+    pub energy_before: Option<f64>,
+    /// The energy of the frame after reaction
+    #[serde(rename = "Energy after")]
+    pub energy_after: Option<f64>,
+    /// The maximum energy of frames in the event window
+    #[serde(rename = "Max energy")]
+    pub max_energy: Option<f64>,
+    /// The maximum energy in the event window relative to the energy
+    /// before reaction, as a rough estimate of reaction barrier
+    #[serde(rename = "Energy barrier")]
+    pub energy_barrier: Option<f64>,
 }
 
-pub struct ReactionWriter {
-    writer: SimpleParquetFileWriter,
+/// The file format for writing reactions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ReactionFormat {
+    #[default]
+    Parquet,
+    Csv,
+    /// JSON Lines: one reaction in JSON per line
+    Jsonl,
+    Sqlite,
+}
+
+impl ReactionFormat {
+    /// Guess the format from file extension of `f`: `csv` for CSV,
+    /// `jsonl` for JSON Lines, `sqlite` or `db` for SQLite, otherwise
+    /// Parquet.
+    pub fn from_path(f: &Path) -> Self {
+        match f.extension().and_then(|x| x.to_str()) {
+            Some("csv") => Self::Csv,
+            Some("jsonl") => Self::Jsonl,
+            Some("sqlite" | "sqlite3" | "db") => Self::Sqlite,
+            _ => Self::Parquet,
+        }
+    }
+}
+
+/// Write reactions into file in Parquet, CSV, JSON Lines or SQLite
+/// format. The columns are the same as in Parquet format for all
+/// formats, with list values written as JSON strings in CSV and SQLite.
+pub enum ReactionWriter {
+    Parquet(ParquetWriter),
+    Csv(std::io::BufWriter<std::fs::File>),
+    JsonLines(std::io::BufWriter<std::fs::File>),
+    /// Reactions are written in table `reactions`.
+    Sqlite(rusqlite::Connection),
 }
 
 impl ReactionWriter {
-    pub fn new(f: &Path) -> Result<Self> {
-        Ok(Self {
-            writer: SimpleParquetFileWriter::new(f),
-        })
+    /// Create a writer for file `f` in `format`. Existing reactions in
+    /// `f` will be overwritten.
+    pub fn with_format(f: &Path, format: ReactionFormat) -> Result<Self> {
+        let create = || std::fs::File::create(f).with_context(|| format!("failed to create {f:?}"));
+        let writer = match format {
+            ReactionFormat::Parquet => Self::Parquet(ParquetWriter::new(f)?),
+            ReactionFormat::Csv => {
+                let mut w = std::io::BufWriter::new(create()?);
+                let header = reaction_columns(&Reaction::default())?.into_iter().map(|(k, _)| csv_field(&k)).join(",");
+                writeln!(w, "{header}")?;
+                Self::Csv(w)
+            }
+            ReactionFormat::Jsonl => Self::JsonLines(std::io::BufWriter::new(create()?)),
+            ReactionFormat::Sqlite => {
+                let conn = rusqlite::Connection::open(f).with_context(|| format!("failed to open {f:?}"))?;
+                let columns = reaction_columns(&Reaction::default())?
+                    .into_iter()
+                    .map(|(k, v)| {
+                        let t = match v {
+                            Value::String(_) | Value::Array(_) | Value::Object(_) => "TEXT",
+                            Value::Bool(_) => "INTEGER",
+                            // NOTE: optional columns could be integers or reals
+                            Value::Number(_) | Value::Null => "NUMERIC",
+                        };
+                        format!("\"{k}\" {t}")
+                    })
+                    .join(", ");
+                conn.execute_batch(&format!(
+                    "DROP TABLE IF EXISTS reactions; CREATE TABLE reactions ({columns});"
+                ))?;
+                Self::Sqlite(conn)
+            }
+        };
+        Ok(writer)
+    }
+
+    /// Reopen file `f` in `format` for appending reactions after
+    /// `position` returned by `Self::position`. The reactions written
+    /// after `position` will be discarded.
+    pub fn resume(f: &Path, format: ReactionFormat, position: u64) -> Result<Self> {
+        let writer = match format {
+            // NOTE: Parquet file cannot be appended, so the reactions
+            // before `position` are read back and rewritten
+            ReactionFormat::Parquet => {
+                let mut reactions = if position > 0 { read_reactions(f)? } else { vec![] };
+                ensure!(
+                    reactions.len() as u64 >= position,
+                    "found only {} reactions in {f:?}, but {position} expected",
+                    reactions.len()
+                );
+                reactions.truncate(position as usize);
+                let mut w = ParquetWriter::new(f)?;
+                w.write_row_group(&reactions)?;
+                Self::Parquet(w)
+            }
+            ReactionFormat::Csv => Self::Csv(reopen_for_append(f, position)?),
+            ReactionFormat::Jsonl => Self::JsonLines(reopen_for_append(f, position)?),
+            ReactionFormat::Sqlite => {
+                let conn = rusqlite::Connection::open(f).with_context(|| format!("failed to open {f:?}"))?;
+                // NOTE: rows are only inserted, so rowid starts from 1
+                // without gaps
+                conn.execute("DELETE FROM reactions WHERE rowid > ?1", [position as i64])?;
+                Self::Sqlite(conn)
+            }
+        };
+        Ok(writer)
+    }
+
+    /// Flush reactions written and return the position for resuming: the
+    /// file size in bytes for CSV and JSON Lines, or the number of rows
+    /// for Parquet and SQLite.
+    pub fn position(&mut self) -> Result<u64> {
+        let position = match self {
+            Self::Parquet(w) => w.nrows,
+            Self::Csv(w) | Self::JsonLines(w) => {
+                w.flush()?;
+                w.get_ref().metadata()?.len()
+            }
+            Self::Sqlite(conn) => conn.query_row("SELECT COUNT(*) FROM reactions", [], |row| row.get::<_, i64>(0))? as u64,
+        };
+        Ok(position)
     }
 
     pub fn write_reactions(&mut self, reactions: &[Reaction]) -> Result<()> {
-        self.writer.write_row_group(reactions)?;
+        match self {
+            Self::Parquet(w) => {
+                w.write_row_group(reactions)?;
+            }
+            Self::Csv(w) => {
+                for r in reactions {
+                    let line = reaction_columns(r)?.into_iter().map(|(_, v)| csv_field(&plain_value(v))).join(",");
+                    writeln!(w, "{line}")?;
+                }
+            }
+            Self::JsonLines(w) => {
+                for r in reactions {
+                    writeln!(w, "{}", serde_json::to_string(r)?)?;
+                }
+            }
+            Self::Sqlite(conn) => {
+                let tx = conn.transaction()?;
+                for r in reactions {
+                    let values = reaction_columns(r)?.into_iter().map(|(_, v)| sql_value(v)).collect_vec();
+                    let params = (1..=values.len()).map(|i| format!("?{i}")).join(", ");
+                    let sql = format!("INSERT INTO reactions VALUES ({params})");
+                    tx.prepare_cached(&sql)?.execute(rusqlite::params_from_iter(values))?;
+                }
+                tx.commit()?;
+            }
+        }
+
+        Ok(())
+    }
 
+    pub fn close(self) -> Result<()> {
+        match self {
+            Self::Parquet(w) => w.close()?,
+            Self::Csv(mut w) | Self::JsonLines(mut w) => w.flush()?,
+            Self::Sqlite(conn) => conn.close().map_err(|(_, e)| e)?,
+        }
         Ok(())
     }
+}
+
+/// Arrow fields of reaction columns in Parquet format. The fields are
+/// defined explicitly instead of traced from reactions, so that row
+/// groups with only empty lists or null values in some columns have the
+/// same schema.
+fn reaction_fields() -> Vec<arrow2::datatypes::Field> {
+    use arrow2::datatypes::{DataType, Field};
+
+    let list = |t: DataType| DataType::LargeList(Box::new(Field::new("element", t, false)));
+    let (int, float, text, boolean) = (DataType::UInt64, DataType::Float64, DataType::LargeUtf8, DataType::Boolean);
+    let atoms = list(list(int.clone()));
+    let names = list(text.clone());
+    #[rustfmt::skip]
+    let fields = [
+        ("Local frame", int.clone(), false),
+        ("Global frame", text.clone(), false),
+        ("Frame", int.clone(), false),
+        ("MD step", int, true),
+        ("Time (fs)", float.clone(), true),
+        ("Time (ps)", float.clone(), true),
+        ("Reactants", atoms.clone(), false),
+        ("Products", atoms.clone(), false),
+        ("Reactants composition", text.clone(), false),
+        ("Products composition", text, false),
+        ("Reactants fingerprints", names.clone(), false),
+        ("Products fingeprints", names.clone(), false),
+        ("Reactants canonical IDs", names.clone(), false),
+        ("Products canonical IDs", names.clone(), false),
+        ("Reactants SMILES", names.clone(), false),
+        ("Products SMILES", names.clone(), false),
+        ("Reactants formulas", names.clone(), false),
+        ("Products formulas", names, false),
+        ("Forming bonds", atoms.clone(), false),
+        ("Breaking bonds", atoms.clone(), false),
+        ("Atom map", atoms, false),
+        ("Atom balanced", boolean.clone(), false),
+        ("Low confidence", boolean, false),
+        ("Energy before", float.clone(), true),
+        ("Energy after", float.clone(), true),
+        ("Max energy", float.clone(), true),
+        ("Energy barrier", float, true),
+    ];
+    fields.into_iter().map(|(name, t, nullable)| Field::new(name, t, nullable)).collect()
+}
+
+/// Write reactions in Parquet format, with the same schema for all row
+/// groups.
+pub struct ParquetWriter {
+    fields: Vec<arrow2::datatypes::Field>,
+    writer: arrow2::io::parquet::write::FileWriter<std::fs::File>,
+    /// The number of reactions written
+    nrows: u64,
+}
+
+impl ParquetWriter {
+    pub fn new(f: &Path) -> Result<Self> {
+        use arrow2::datatypes::Schema;
+        use arrow2::io::parquet::write::{CompressionOptions, FileWriter, Version, WriteOptions};
+
+        let file = std::fs::File::create(f).with_context(|| format!("failed to create {f:?}"))?;
+        let fields = reaction_fields();
+        let options = WriteOptions {
+            write_statistics: false,
+            compression: CompressionOptions::Snappy,
+            version: Version::V2,
+            data_pagesize_limit: None,
+        };
+        let writer = FileWriter::try_new(file, Schema::from(fields.clone()), options)?;
+        Ok(Self { fields, writer, nrows: 0 })
+    }
 
+    /// Write `reactions` in a row group.
+    pub fn write_row_group(&mut self, reactions: &[Reaction]) -> Result<()> {
+        use arrow2::chunk::Chunk;
+        use arrow2::io::parquet::write::{transverse, Encoding, RowGroupIterator};
+
+        if reactions.is_empty() {
+            return Ok(());
+        }
+        let arrays = serde_arrow::to_arrow2(&self.fields, reactions)?;
+        let schema = self.writer.schema();
+        let encodings = schema.fields.iter().map(|f| transverse(&f.data_type, |_| Encoding::Plain)).collect();
+        let chunks = std::iter::once(Ok(Chunk::new(arrays)));
+        let row_groups = RowGroupIterator::try_new(chunks, schema, self.writer.options(), encodings)?;
+        for group in row_groups {
+            self.writer.write(group?)?;
+        }
+        self.nrows += reactions.len() as u64;
+        Ok(())
+    }
+
+    /// Write the footer of Parquet file, which is required for reading.
     pub fn close(mut self) -> Result<()> {
-        self.writer.close();
+        self.writer.end(None)?;
         Ok(())
     }
 }
+
+/// Reopen file `f` for appending from `position` in bytes. The contents
+/// after `position` will be discarded.
+pub fn reopen_for_append(f: &Path, position: u64) -> Result<std::io::BufWriter<std::fs::File>> {
+    let file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(f)
+        .with_context(|| format!("failed to open {f:?}"))?;
+    file.set_len(position)?;
+    Ok(std::io::BufWriter::new(file))
+}
+
+/// Return column names and values of `reaction` in the order of struct
+/// fields.
+fn reaction_columns(reaction: &Reaction) -> Result<Vec<(String, Value)>> {
+    match serde_json::to_value(reaction)? {
+        Value::Object(map) => Ok(map.into_iter().collect()),
+        _ => bail!("invalid reaction data: {reaction:?}"),
+    }
+}
+
+/// Format JSON value as plain text: empty for null, unquoted for string,
+/// and JSON for others.
+fn plain_value(v: Value) -> String {
+    match v {
+        Value::Null => String::new(),
+        Value::String(s) => s,
+        v => v.to_string(),
+    }
+}
+
+/// Quote CSV field `s` if required.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn sql_value(v: Value) -> rusqlite::types::Value {
+    use rusqlite::types::Value as SqlValue;
+
+    match v {
+        Value::Null => SqlValue::Null,
+        Value::Bool(b) => SqlValue::Integer(b as i64),
+        Value::Number(x) => match x.as_i64() {
+            Some(i) => SqlValue::Integer(i),
+            None => SqlValue::Real(x.as_f64().unwrap_or(f64::NAN)),
+        },
+        Value::String(s) => SqlValue::Text(s),
+        v => SqlValue::Text(v.to_string()),
+    }
+}
+
+/// Read all reactions from Parquet file `f` written by `ReactionWriter`.
+pub fn read_reactions(f: &Path) -> Result<Vec<Reaction>> {
+    read_parquet_rows(f)
+}
+
+/// Read all rows in Parquet file `f` as items in type `T`.
+pub fn read_parquet_rows<T: serde::de::DeserializeOwned>(f: &Path) -> Result<Vec<T>> {
+    use arrow2::io::parquet::read;
+
+    let mut reader = std::fs::File::open(f).with_context(|| format!("failed to open {f:?}"))?;
+    let metadata = read::read_metadata(&mut reader).with_context(|| format!("invalid Parquet file: {f:?}"))?;
+    let schema = read::infer_schema(&metadata)?;
+    let chunks = read::FileReader::new(reader, metadata.row_groups, schema.clone(), None, None, None);
+    let mut rows = vec![];
+    for chunk in chunks {
+        let chunk = chunk?;
+        let items: Vec<T> = serde_arrow::from_arrow2(&schema.fields, chunk.arrays())?;
+        rows.extend(items);
+    }
+    Ok(rows)
+}
+
+/// Return a reaction in `frame` for tests. The species names in
+/// `reactants` and `products` are used as fingerprints and canonical
+/// identifiers, and in uppercase as formulas.
+#[cfg(test)]
+pub(crate) fn test_reaction(frame: usize, reactants: &[&str], products: &[&str]) -> Reaction {
+    let names = |x: &[&str]| x.iter().map(|x| x.to_string()).collect_vec();
+    let formulas = |x: &[&str]| x.iter().map(|x| x.to_uppercase()).collect_vec();
+    Reaction {
+        global_frame: frame.to_string(),
+        frame,
+        reactants_fingerprints: names(reactants),
+        products_fingerprints: names(products),
+        reactants_canonical_ids: names(reactants),
+        products_canonical_ids: names(products),
+        reactants_formulas: formulas(reactants),
+        products_formulas: formulas(products),
+        ..Default::default()
+    }
+}
 // 6e775d47 ends here
 
+// [[file:../../trajectory.note::3b7e0f52][3b7e0f52]]
+#[test]
+fn test_reaction_writer() -> Result<()> {
+    let reaction = Reaction {
+        global_frame: "9".into(),
+        frame: 9,
+        time_fs: Some(4.5),
+        reactants: vec![vec![1], vec![2]],
+        products: vec![vec![1, 2]],
+        reactants_formulas: vec!["H".into(), "H".into()],
+        products_formulas: vec!["H2".into()],
+        ..Default::default()
+    };
+    let reactions = vec![reaction.clone(), reaction];
+    let columns = reaction_columns(&reactions[0])?.into_iter().map(|(k, _)| k).collect_vec();
+    assert_eq!(columns[..3], ["Local frame", "Global frame", "Frame"]);
+
+    let tdir = gut::fs::tempfile::tempdir()?;
+    for ext in ["csv", "jsonl", "db"] {
+        let f = tdir.path().join(format!("reaction.{ext}"));
+        let mut writer = ReactionWriter::with_format(&f, ReactionFormat::from_path(&f))?;
+        writer.write_reactions(&reactions)?;
+        writer.close()?;
+    }
+
+    let s = gut::fs::read_file(tdir.path().join("reaction.csv"))?;
+    let lines = s.lines().collect_vec();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("Local frame,Global frame,Frame,MD step,Time (fs),"));
+    assert!(lines[1].starts_with(r#"0,9,9,,4.5,,"[[1],[2]]","[[1,2]]","#));
+
+    let s = gut::fs::read_file(tdir.path().join("reaction.jsonl"))?;
+    let items: Vec<Reaction> = s.lines().map(serde_json::from_str).try_collect()?;
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[1].products_formulas, ["H2"]);
+
+    let conn = rusqlite::Connection::open(tdir.path().join("reaction.db"))?;
+    let (n, time, products): (usize, f64, String) = conn.query_row(
+        r#"SELECT COUNT(*), "Time (fs)", "Products" FROM reactions"#,
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    assert_eq!(n, 2);
+    assert_eq!(time, 4.5);
+    assert_eq!(products, "[[1,2]]");
+
+    Ok(())
+}
+// 3b7e0f52 ends here
+
+// [[file:../../trajectory.note::c4a86e1d][c4a86e1d]]
+#[test]
+fn test_reaction_parquet_writer() -> Result<()> {
+    // no bond formed and no energy in the first row group
+    let breaking = Reaction {
+        global_frame: "3".into(),
+        reactants: vec![vec![1, 2]],
+        products: vec![vec![1], vec![2]],
+        breaking_bonds: vec![vec![1, 2]],
+        ..Default::default()
+    };
+    let forming = Reaction {
+        global_frame: "5".into(),
+        md_step: Some(50),
+        reactants: breaking.products.clone(),
+        products: breaking.reactants.clone(),
+        forming_bonds: vec![vec![1, 2]],
+        energy_before: Some(-1.5),
+        ..Default::default()
+    };
+
+    let tdir = gut::fs::tempfile::tempdir()?;
+    let f = tdir.path().join("reaction.pq");
+    let mut writer = ReactionWriter::with_format(&f, ReactionFormat::Parquet)?;
+    writer.write_reactions(&[breaking])?;
+    writer.write_reactions(&[forming])?;
+    writer.close()?;
+
+    let reactions = read_reactions(&f)?;
+    assert_eq!(reactions.len(), 2);
+    assert_eq!(reactions[0].breaking_bonds, [[1, 2]]);
+    assert!(reactions[0].forming_bonds.is_empty());
+    assert_eq!(reactions[0].energy_before, None);
+    assert_eq!(reactions[1].forming_bonds, [[1, 2]]);
+    assert!(reactions[1].breaking_bonds.is_empty());
+    assert_eq!(reactions[1].md_step, Some(50));
+    assert_eq!(reactions[1].energy_before, Some(-1.5));
+
+    // all columns are defined
+    let columns = reaction_columns(&Reaction::default())?.into_iter().map(|(k, _)| k).collect_vec();
+    let fields = reaction_fields().into_iter().map(|x| x.name).collect_vec();
+    assert_eq!(fields, columns);
+
+    Ok(())
+}
+// c4a86e1d ends here
+
+// [[file:../../trajectory.note::c3a9e6f1][c3a9e6f1]]
+/// Read MD timestep from xyz title line, such as "Atoms. Timestep: 100"
+/// written by LAMMPS.
+pub fn read_timestep_from_title(title: &str) -> Option<usize> {
+    use regex::Regex;
+    use std::sync::OnceLock;
+
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r"(?i)timestep\s*[:=]?\s*(\d+)").unwrap());
+    re.captures(title)?.get(1)?.as_str().parse().ok()
+}
+
+#[test]
+fn test_read_timestep_from_title() {
+    assert_eq!(read_timestep_from_title("Atoms. Timestep: 100"), Some(100));
+    assert_eq!(read_timestep_from_title("timestep=20 energy=-1.2"), Some(20));
+    assert_eq!(read_timestep_from_title("frame 1"), None);
+}
+
+/// The frame index, MD step and simulation time of one trajectory frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct FrameTime {
+    /// The frame index in trajectory file counting from 0
+    pub frame: usize,
+    pub md_step: Option<usize>,
+    /// The simulation time in fs
+    pub time: Option<f64>,
+}
+
+impl FrameTime {
+    /// Read MD step and time from `title` of the `frame`-th frame in
+    /// trajectory file. The title could be written by LAMMPS
+    /// ("Timestep: 100"), CP2K ("i = 100, time = 50.000, E = ...") or
+    /// in extxyz format ("Step=100 Time=50.0"), and the time is assumed
+    /// in fs. If no time found, it will be computed from MD `timestep`
+    /// in fs as `timestep * md_step`, or as `timestep * frame` if no MD
+    /// step found either, assuming frames written at every MD step.
+    pub fn from_title(frame: usize, title: &str, timestep: Option<f64>) -> Self {
+        use regex::Regex;
+        use std::sync::OnceLock;
+
+        static STEP_RE: OnceLock<Regex> = OnceLock::new();
+        static TIME_RE: OnceLock<Regex> = OnceLock::new();
+        let md_step = read_timestep_from_title(title).or_else(|| {
+            let re = STEP_RE.get_or_init(|| Regex::new(r"(?i)(?:^|[\s,])(?:i|step)\s*=\s*(\d+)").unwrap());
+            re.captures(title)?.get(1)?.as_str().parse().ok()
+        });
+        let re = TIME_RE.get_or_init(|| Regex::new(r"(?i)(?:^|[\s,])time\s*=\s*([-+]?[0-9.]+(?:[eE][-+]?\d+)?)").unwrap());
+        let time = re
+            .captures(title)
+            .and_then(|x| x.get(1)?.as_str().parse().ok())
+            .or_else(|| Some(timestep? * md_step.unwrap_or(frame) as f64));
+        Self { frame, md_step, time }
+    }
+}
+
+impl Reaction {
+    /// Set frame index, MD step and time columns from `t`.
+    pub fn set_frame_time(&mut self, t: FrameTime) {
+        self.frame = t.frame;
+        self.md_step = t.md_step;
+        self.time_fs = t.time;
+        self.time_ps = t.time.map(|x| x / 1000.0);
+    }
+}
+
+#[test]
+fn test_frame_time() {
+    let t = FrameTime::from_title(3, "Timestep: 100", Some(0.5));
+    assert_eq!(t.md_step, Some(100));
+    assert_eq!(t.time, Some(50.0));
+    assert_eq!(FrameTime::from_title(3, "frame 3", Some(0.5)).time, Some(1.5));
+    let t = FrameTime::from_title(3, " i =      200, time =      100.000, E =     -1234.56", None);
+    assert_eq!(t.md_step, Some(200));
+    assert_eq!(t.time, Some(100.0));
+    let t = FrameTime::from_title(3, "Lattice=\"10 0 0 0 10 0 0 0 10\" Step=300 Time=150.5", None);
+    assert_eq!(t.md_step, Some(300));
+    assert_eq!(t.time, Some(150.5));
+    assert_eq!(FrameTime::from_title(3, "frame 3", None), FrameTime { frame: 3, ..Default::default() });
+}
+// c3a9e6f1 ends here
+
+// [[file:../../trajectory.note::8e3f5a27][8e3f5a27]]
+use std::collections::BTreeMap;
+
+/// Read key=value frame properties from xyz title line in extxyz
+/// format, such as `Lattice="10 0 0 0 10 0 0 0 10" energy=-1.2`.
+/// Spaces around `=` are allowed as in CP2K comment line (`E =
+/// -1234.5`), and quotes around value will be removed.
+pub fn read_frame_properties(title: &str) -> BTreeMap<String, String> {
+    use regex::Regex;
+    use std::sync::OnceLock;
+
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r#"([A-Za-z_][\w:.-]*)\s*=\s*(?:"([^"]*)"|([^\s,]+))"#).unwrap());
+    re.captures_iter(title)
+        .filter_map(|x| {
+            let value = x.get(2).or_else(|| x.get(3))?.as_str();
+            Some((x[1].to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// Return the energy in frame `properties` (key `energy`, or `E` in
+/// CP2K comment line), ignoring case.
+pub fn get_frame_energy(properties: &BTreeMap<String, String>) -> Option<f64> {
+    properties
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("energy") || k.eq_ignore_ascii_case("e"))
+        .and_then(|(_, v)| v.parse().ok())
+}
+
+#[test]
+fn test_read_frame_properties() {
+    let props = read_frame_properties(r#"Lattice="10 0 0 0 10 0 0 0 10" Properties=species:S:1:pos:R:3 energy=-1.25 pbc="T T T""#);
+    assert_eq!(props["Lattice"], "10 0 0 0 10 0 0 0 10");
+    assert_eq!(props["Properties"], "species:S:1:pos:R:3");
+    assert_eq!(props["pbc"], "T T T");
+    assert_eq!(get_frame_energy(&props), Some(-1.25));
+
+    let props = read_frame_properties(" i =      200, time =      100.000, E =     -1234.56");
+    assert_eq!(props["i"], "200");
+    assert_eq!(get_frame_energy(&props), Some(-1234.56));
+    assert_eq!(get_frame_energy(&read_frame_properties("frame 3")), None);
+}
+// 8e3f5a27 ends here
+
 // [[file:../../trajectory.note::7e14952a][7e14952a]]
 use gchemol::Molecule;
 
@@ -0,0 +1,224 @@
+// [[file:../../trajectory.note::4c7d2e10][4c7d2e10]]
+use super::io::Reaction;
+use super::population::SpeciesPopulation;
+use crate::common::*;
+
+use indexmap::IndexMap;
+use std::collections::BTreeMap;
+// 4c7d2e10 ends here
+
+// [[file:../../trajectory.note::9e3b5f62][9e3b5f62]]
+/// Rate constant estimated from reaction events of one unique reaction.
+#[derive(Debug, Clone, Serialize)]
+pub struct RateConstant {
+    #[serde(rename = "Reaction")]
+    pub equation: String,
+    /// The number of reactant molecules
+    #[serde(rename = "Order")]
+    pub order: usize,
+    /// The number of reaction events
+    #[serde(rename = "Count")]
+    pub count: usize,
+    /// Rate constant in s^-1 for first-order reaction, and in
+    /// cm^3/(molecule s) for second-order reaction
+    #[serde(rename = "Rate constant")]
+    pub rate_constant: Option<f64>,
+    /// Standard error of rate constant assuming Poisson statistics of
+    /// reaction events
+    #[serde(rename = "Uncertainty")]
+    pub uncertainty: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ReactionCount {
+    reactants: Vec<String>,
+    products: Vec<String>,
+    count: usize,
+}
+
+/// Collect reaction events and species population for estimating rate
+/// constants.
+///
+/// For first-order reaction A => products, k = n / ∫N(A)dt, and for
+/// second-order reaction A + B => products, k = n V / ∫N(A)N(B)dt,
+/// where n is the number of reaction events. For A + A, the number of
+/// distinct pairs N(A)(N(A)-1)/2 is used instead of N(A)N(B).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReactionKinetics {
+    // key: sorted canonical identifiers of reactants and products, the
+    // same as in unique reactions summary
+    #[serde(with = "indexmap::serde_seq")]
+    reactions: IndexMap<[Vec<String>; 2], ReactionCount>,
+    // the number of frames for each distinct species population keyed
+    // by formula, which changes only when reactions occur
+    #[serde(with = "indexmap::serde_seq")]
+    populations: IndexMap<BTreeMap<String, usize>, usize>,
+    // the first and last time in fs of analyzed frames, and the number
+    // of frames in between
+    frame_times: Option<([f64; 2], usize)>,
+}
+// 9e3b5f62 ends here
+
+// [[file:../../trajectory.note::a05d81c7][a05d81c7]]
+impl ReactionKinetics {
+    /// Add one `reaction` event. Reactions without reactants or
+    /// products will be ignored.
+    pub fn add_reaction(&mut self, reaction: &Reaction) {
+        if reaction.reactants_canonical_ids.is_empty() || reaction.products_canonical_ids.is_empty() {
+            return;
+        }
+        let sorted = |ids: &[String], formulas: &[String]| -> (Vec<String>, Vec<String>) {
+            ids.iter().cloned().zip(formulas.iter().cloned()).sorted().unzip()
+        };
+        let (rids, rformulas) = sorted(&reaction.reactants_canonical_ids, &reaction.reactants_formulas);
+        let (pids, pformulas) = sorted(&reaction.products_canonical_ids, &reaction.products_formulas);
+        let entry = self.reactions.entry([rids, pids]).or_insert_with(|| ReactionCount {
+            reactants: rformulas,
+            products: pformulas,
+            count: 0,
+        });
+        entry.count += 1;
+    }
+
+    /// Add species `population` in frame order.
+    pub fn add_population(&mut self, population: &[SpeciesPopulation]) {
+        for (_, group) in &population.iter().group_by(|x| &x.global_frame) {
+            let counts = group.map(|x| (x.species.clone(), x.count)).collect();
+            *self.populations.entry(counts).or_default() += 1;
+        }
+    }
+
+    /// Add the simulation `time` in fs of the next analyzed frame in
+    /// trajectory order.
+    pub fn add_frame_time(&mut self, time: f64) {
+        match self.frame_times.as_mut() {
+            Some(([_, last], n)) => {
+                *last = time;
+                *n += 1;
+            }
+            None => self.frame_times = Some(([time, time], 1)),
+        }
+    }
+
+    /// Return the mean time interval in fs between consecutive analyzed
+    /// frames from frame times added.
+    pub fn frame_interval(&self) -> Option<f64> {
+        let ([first, last], n) = self.frame_times?;
+        let dt = (n > 1).then(|| (last - first) / (n - 1) as f64)?;
+        (dt > 0.0).then_some(dt)
+    }
+
+    /// The time integral of species population in frames, using
+    /// `pairs` for the number of reactant pairs in one frame.
+    fn exposure(&self, pairs: impl Fn(&BTreeMap<String, usize>) -> f64) -> f64 {
+        self.populations.iter().map(|(x, &n)| pairs(x) * n as f64).sum()
+    }
+
+    /// Return estimated rate constants for all unique reactions.
+    ///
+    /// # Parameters
+    /// * dt: the time interval between two analyzed frames in fs.
+    /// * volume: the volume of simulation box in Å^3, required for
+    ///   second-order reactions.
+    pub fn rate_constants(&self, dt: f64, volume: Option<f64>) -> Vec<RateConstant> {
+        // fs => s
+        let dt = dt * 1e-15;
+        // Å^3 => cm^3
+        let volume = volume.map(|v| v * 1e-24);
+
+        let get = |counts: &BTreeMap<String, usize>, k: &str| counts.get(k).copied().unwrap_or(0) as f64;
+        self.reactions
+            .values()
+            .map(|r| {
+                let order = r.reactants.len();
+                let exposure = match (&r.reactants[..], volume) {
+                    ([a], _) => Some(self.exposure(|x| get(x, a)) * dt),
+                    ([a, b], Some(v)) => {
+                        if a == b {
+                            Some(self.exposure(|x| get(x, a) * (get(x, a) - 1.0).max(0.0) / 2.0) * dt / v)
+                        } else {
+                            Some(self.exposure(|x| get(x, a) * get(x, b)) * dt / v)
+                        }
+                    }
+                    _ => None,
+                };
+                let n = r.count as f64;
+                let rate_constant = exposure.filter(|&x| x > 0.0).map(|x| n / x);
+                RateConstant {
+                    equation: super::format_equation(&r.reactants, &r.products),
+                    order,
+                    count: r.count,
+                    rate_constant,
+                    uncertainty: rate_constant.map(|k| k / n.sqrt()),
+                }
+            })
+            .collect()
+    }
+
+    /// Write rate constants into file `f` in CSV format.
+    pub fn write_rate_constants(&self, f: &Path, dt: f64, volume: Option<f64>) -> Result<()> {
+        let mut s = String::new();
+        writeln!(s, "Reaction,Order,Count,Rate constant,Uncertainty")?;
+        let fmt = |x: Option<f64>| x.map(|x| format!("{x:.6e}")).unwrap_or_default();
+        for x in self.rate_constants(dt, volume) {
+            let k = fmt(x.rate_constant);
+            let e = fmt(x.uncertainty);
+            writeln!(s, "{},{},{},{k},{e}", x.equation, x.order, x.count)?;
+        }
+        gut::fs::write_to_file(f, &s)?;
+        Ok(())
+    }
+}
+// a05d81c7 ends here
+
+// [[file:../../trajectory.note::f1c8b3a4][f1c8b3a4]]
+#[test]
+fn test_rate_constants() {
+    use super::io::test_reaction as reaction;
+
+    let population = |frame: usize, h: usize, h2: usize| {
+        vec![
+            SpeciesPopulation {
+                global_frame: frame.to_string(),
+                species: "H".into(),
+                count: h,
+            },
+            SpeciesPopulation {
+                global_frame: frame.to_string(),
+                species: "H2".into(),
+                count: h2,
+            },
+        ]
+    };
+
+    let mut kinetics = ReactionKinetics::default();
+    for i in 0..10 {
+        kinetics.add_population(&population(i, 4, 2));
+    }
+    for i in 0..4 {
+        // the same reaction with reactant in different conformations
+        let mut r = reaction(0, &["H2"], &["H", "H"]);
+        r.reactants_fingerprints = vec![format!("H2-{i}")];
+        kinetics.add_reaction(&r);
+    }
+    kinetics.add_reaction(&reaction(0, &["H", "H"], &["H2"]));
+
+    let rates = kinetics.rate_constants(1.0, None);
+    assert_eq!(rates.len(), 2);
+    // 4 events / (2 molecules * 10 frames * 1 fs)
+    assert_relative_eq!(rates[0].rate_constant.unwrap(), 0.2e15, max_relative = 1e-6);
+    assert_relative_eq!(rates[0].uncertainty.unwrap(), 0.1e15, max_relative = 1e-6);
+    assert!(rates[1].rate_constant.is_none());
+
+    // 1 event * V / (6 pairs * 10 frames * 1 fs)
+    let rates = kinetics.rate_constants(1.0, Some(1000.0));
+    assert_relative_eq!(rates[1].rate_constant.unwrap(), 1e-21 / 60.0 * 1e15, max_relative = 1e-6);
+
+    // frames written every 50 fs
+    assert_eq!(kinetics.frame_interval(), None);
+    for i in 0..10 {
+        kinetics.add_frame_time(100.0 + i as f64 * 50.0);
+    }
+    assert_relative_eq!(kinetics.frame_interval().unwrap(), 50.0);
+}
+// f1c8b3a4 ends here
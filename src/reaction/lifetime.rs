@@ -0,0 +1,348 @@
+// [[file:../../trajectory.note::9a4c1e73][9a4c1e73]]
+use crate::common::*;
+
+use gchemol::Molecule;
+use std::collections::BTreeMap;
+// 9a4c1e73 ends here
+
+// [[file:../../trajectory.note::2f8b6d05][2f8b6d05]]
+/// One fragment instance identified by its atom set, from formation to
+/// destruction.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpeciesInstance {
+    /// The chemical formula of the fragment
+    pub species: String,
+    /// Atoms in the fragment
+    pub atoms: Vec<usize>,
+    /// The first global frame the fragment exists
+    pub first_frame: usize,
+    /// The last global frame the fragment exists
+    pub last_frame: usize,
+}
+
+impl SpeciesInstance {
+    /// The number of frames the fragment exists.
+    pub fn lifetime(&self) -> usize {
+        self.last_frame - self.first_frame + 1
+    }
+}
+
+/// Lifetime statistics of one species
+#[derive(Debug, Clone, Serialize)]
+pub struct SpeciesLifetime {
+    #[serde(rename = "Species")]
+    pub species: String,
+    /// The number of fragment instances
+    #[serde(rename = "Instances")]
+    pub instances: usize,
+    /// The number of instances formed and destroyed in the trajectory
+    #[serde(rename = "Complete")]
+    pub complete: usize,
+    /// The number of instances existing in the first frame
+    #[serde(rename = "Left censored")]
+    pub left_censored: usize,
+    /// The number of instances existing in the last frame
+    #[serde(rename = "Right censored")]
+    pub right_censored: usize,
+    /// The total number of frames observed in all instances divided by
+    /// the number of destruction events, which is the maximum
+    /// likelihood estimate for exponentially distributed lifetimes.
+    /// None if no instance destroyed.
+    #[serde(rename = "Mean lifetime")]
+    pub mean_lifetime: Option<f64>,
+    /// The mean lifetime of complete instances only, which is biased
+    /// toward short lifetimes.
+    #[serde(rename = "Mean complete lifetime")]
+    pub mean_complete_lifetime: Option<f64>,
+}
+
+/// Track fragment instances in consecutive frames for species lifetime
+/// analysis.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SpeciesLifetimes {
+    /// The first frame added
+    start: Option<usize>,
+    /// The last frame added
+    end: Option<usize>,
+    /// Instances existing in the last frame
+    alive: Vec<SpeciesInstance>,
+    /// Instances destroyed
+    destroyed: Vec<SpeciesInstance>,
+    /// The number of complete and left censored instances taken out of
+    /// `destroyed`, for each species and lifetime
+    taken: BTreeMap<String, BTreeMap<usize, [usize; 2]>>,
+}
+
+impl SpeciesLifetimes {
+    /// Update fragment instances using fragments in `mol` in global
+    /// `frame`. Frames should be added in trajectory order without
+    /// gaps.
+    pub fn add_frame(&mut self, frame: usize, mol: &Molecule) {
+        let mut alive: HashMap<Vec<usize>, SpeciesInstance> =
+            self.alive.drain(..).map(|x| (x.atoms.clone(), x)).collect();
+        for frag in mol.fragmented() {
+            let atoms = frag.numbers().sorted().collect_vec();
+            let instance = match alive.remove(&atoms) {
+                Some(mut x) => {
+                    x.last_frame = frame;
+                    x
+                }
+                None => SpeciesInstance {
+                    species: frag.formula(),
+                    atoms,
+                    first_frame: frame,
+                    last_frame: frame,
+                },
+            };
+            self.alive.push(instance);
+        }
+        self.destroyed.extend(alive.into_values());
+        self.start = self.start.or(Some(frame));
+        self.end = Some(frame);
+    }
+
+    /// Append instances in `other` tracked for the following frames.
+    /// Instances existing on both sides of the boundary are joined.
+    pub fn merge(&mut self, mut other: Self) {
+        let start = match other.start {
+            Some(start) => start,
+            None => return,
+        };
+        let mut alive: HashMap<Vec<usize>, SpeciesInstance> =
+            self.alive.drain(..).map(|x| (x.atoms.clone(), x)).collect();
+        for x in other.alive.iter_mut().chain(other.destroyed.iter_mut()) {
+            if x.first_frame == start {
+                if let Some(y) = alive.remove(&x.atoms) {
+                    x.first_frame = y.first_frame;
+                }
+            }
+        }
+        self.destroyed.extend(alive.into_values());
+        self.destroyed.extend(other.destroyed);
+        self.alive = other.alive;
+        self.start = self.start.or(other.start);
+        self.end = other.end;
+    }
+
+    /// Return all instances with flags for existing in the first frame
+    /// and in the last frame, in order of formation. Instances taken
+    /// out by `take_destroyed` are not included.
+    pub fn instances(&self) -> Vec<(&SpeciesInstance, [bool; 2])> {
+        let destroyed = self.destroyed.iter().map(|x| (x, [Some(x.first_frame) == self.start, false]));
+        let alive = self.alive.iter().map(|x| (x, [Some(x.first_frame) == self.start, true]));
+        destroyed
+            .chain(alive)
+            .sorted_by_key(|(x, _)| (x.first_frame, x.atoms.clone()))
+            .collect()
+    }
+
+    /// Take out destroyed instances with flags as in `instances`, in
+    /// order of destruction. Only their numbers are kept for `summary`
+    /// and `distribution`, which saves memory for long trajectory.
+    pub fn take_destroyed(&mut self) -> Vec<(SpeciesInstance, [bool; 2])> {
+        let destroyed = self
+            .destroyed
+            .drain(..)
+            .sorted_by_key(|x| (x.last_frame, x.atoms.clone()))
+            .map(|x| {
+                let left = Some(x.first_frame) == self.start;
+                (x, [left, false])
+            })
+            .collect_vec();
+        for (x, [left, _]) in &destroyed {
+            let counts = self.taken.entry(x.species.clone()).or_default();
+            counts.entry(x.lifetime()).or_default()[*left as usize] += 1;
+        }
+        destroyed
+    }
+
+    /// Return the number of instances for each species, lifetime and
+    /// flags as in `instances`, including instances taken out.
+    fn counts(&self) -> BTreeMap<(&str, usize, [bool; 2]), usize> {
+        let mut counts = BTreeMap::new();
+        for (species, x) in &self.taken {
+            for (&lifetime, &[complete, left_censored]) in x {
+                *counts.entry((species.as_str(), lifetime, [false, false])).or_default() += complete;
+                *counts.entry((species.as_str(), lifetime, [true, false])).or_default() += left_censored;
+            }
+        }
+        for (x, censored) in self.instances() {
+            *counts.entry((x.species.as_str(), x.lifetime(), censored)).or_default() += 1;
+        }
+        counts.retain(|_, n| *n > 0);
+        counts
+    }
+
+    /// Return lifetime statistics for each species.
+    pub fn summary(&self) -> Vec<SpeciesLifetime> {
+        let mut stats: BTreeMap<&str, Vec<_>> = BTreeMap::new();
+        for ((species, lifetime, censored), n) in self.counts() {
+            stats.entry(species).or_default().push((lifetime, censored, n));
+        }
+        let mean = |total: usize, n: usize| (n > 0).then(|| total as f64 / n as f64);
+        let sum = |lifetimes: &[(usize, [bool; 2], usize)], f: fn(&[bool; 2]) -> bool| -> [usize; 2] {
+            lifetimes
+                .iter()
+                .filter(|(_, c, _)| f(c))
+                .fold([0, 0], |[n, total], (t, _, k)| [n + k, total + t * k])
+        };
+        stats
+            .into_iter()
+            .map(|(species, lifetimes)| {
+                let [instances, total] = sum(&lifetimes, |_| true);
+                let [complete, complete_total] = sum(&lifetimes, |c| c == &[false, false]);
+                let [right_censored, _] = sum(&lifetimes, |c| c[1]);
+                SpeciesLifetime {
+                    species: species.to_string(),
+                    instances,
+                    complete,
+                    left_censored: sum(&lifetimes, |c| c[0])[0],
+                    right_censored,
+                    mean_lifetime: mean(total, instances - right_censored),
+                    mean_complete_lifetime: mean(complete_total, complete),
+                }
+            })
+            .collect()
+    }
+
+    /// Return the number of complete and censored instances of each
+    /// species for each lifetime.
+    pub fn distribution(&self) -> BTreeMap<(String, usize), [usize; 2]> {
+        let mut counts: BTreeMap<_, [usize; 2]> = BTreeMap::new();
+        for ((species, lifetime, censored), n) in self.counts() {
+            let i = if censored == [false, false] { 0 } else { 1 };
+            counts.entry((species.to_string(), lifetime)).or_default()[i] += n;
+        }
+        counts
+    }
+
+    /// Write lifetime statistics into CSV file `f`. The lifetime
+    /// distribution is written into file with the extension replaced by
+    /// `distribution.csv`. Lifetimes are in frames. The instances are
+    /// written by `InstanceWriter`.
+    pub fn write_files(&self, f: &Path) -> Result<()> {
+        let mut s = String::new();
+        writeln!(s, "Species,Instances,Complete,Left censored,Right censored,Mean lifetime,Mean complete lifetime")?;
+        let format_mean = |x: Option<f64>| x.map(|x| format!("{x:.2}")).unwrap_or_default();
+        for x in self.summary() {
+            let (mean, mean_complete) = (format_mean(x.mean_lifetime), format_mean(x.mean_complete_lifetime));
+            let (n, nc, nl, nr) = (x.instances, x.complete, x.left_censored, x.right_censored);
+            writeln!(s, "{},{n},{nc},{nl},{nr},{mean},{mean_complete}", x.species)?;
+        }
+        gut::fs::write_to_file(f, &s)?;
+
+        let mut s = String::new();
+        writeln!(s, "Species,Lifetime,Complete,Censored")?;
+        for ((species, lifetime), [complete, censored]) in self.distribution() {
+            writeln!(s, "{species},{lifetime},{complete},{censored}")?;
+        }
+        gut::fs::write_to_file(&f.with_extension("distribution.csv"), &s)?;
+
+        Ok(())
+    }
+}
+
+/// Write fragment instances into CSV file, which could be appended when
+/// resuming.
+pub struct InstanceWriter(std::io::BufWriter<std::fs::File>);
+
+impl InstanceWriter {
+    /// The file for writing instances, with the extension of lifetimes
+    /// file `f` replaced by `instances.csv`.
+    pub fn instances_file(f: &Path) -> PathBuf {
+        f.with_extension("instances.csv")
+    }
+
+    pub fn new(f: &Path) -> Result<Self> {
+        let mut w = std::io::BufWriter::new(std::fs::File::create(f)?);
+        writeln!(w, "Species,Atoms,First frame,Last frame,Lifetime,Left censored,Right censored")?;
+        Ok(Self(w))
+    }
+
+    /// Reopen file `f` for appending instances after `position` returned
+    /// by `Self::position`.
+    pub fn resume(f: &Path, position: u64) -> Result<Self> {
+        let w = super::io::reopen_for_append(f, position)?;
+        Ok(Self(w))
+    }
+
+    pub fn write_instances<'a>(&mut self, instances: impl IntoIterator<Item = (&'a SpeciesInstance, [bool; 2])>) -> Result<()> {
+        for (x, [left, right]) in instances {
+            let atoms = x.atoms.iter().join(" ");
+            let (first, last, lifetime) = (x.first_frame, x.last_frame, x.lifetime());
+            writeln!(self.0, "{},{atoms},{first},{last},{lifetime},{left},{right}", x.species)?;
+        }
+        Ok(())
+    }
+
+    /// Flush instances written and return the file size in bytes for
+    /// resuming.
+    pub fn position(&mut self) -> Result<u64> {
+        self.0.flush()?;
+        Ok(self.0.get_ref().metadata()?.len())
+    }
+
+    pub fn close(mut self) -> Result<()> {
+        self.0.flush()?;
+        Ok(())
+    }
+}
+// 2f8b6d05 ends here
+
+// [[file:../../trajectory.note::71d0e4b8][71d0e4b8]]
+#[test]
+fn test_species_lifetimes() {
+    use gchemol::{Atom, Bond};
+
+    let atoms = [[0.0, 0.0, 0.0], [0.0, 0.0, 0.7], [0.0, 0.0, 1.4]].map(|p| Atom::new("H", p));
+    let mol = Molecule::from_atoms(atoms);
+    let mut mol1 = mol.clone();
+    mol1.add_bond(1, 2, Bond::default());
+    let mut mol2 = mol.clone();
+    mol2.add_bond(2, 3, Bond::default());
+    // H2(1-2) + H(3) => H(1) + H2(2-3) => H2(1-2) + H(3)
+    let mols = [&mol1, &mol1, &mol2, &mol2, &mol2, &mol1];
+
+    let mut lifetimes = SpeciesLifetimes::default();
+    for (i, mol) in mols.iter().enumerate() {
+        lifetimes.add_frame(i, mol);
+    }
+    let instances = lifetimes.instances();
+    assert_eq!(instances.len(), 6);
+    // H2(1-2) existing in the first frame
+    assert_eq!(instances[0].0.atoms, [1, 2]);
+    assert_eq!(instances[0].0.lifetime(), 2);
+    assert_eq!(instances[0].1, [true, false]);
+
+    let summary = lifetimes.summary();
+    let h2 = summary.iter().find(|x| x.species == "H2").unwrap();
+    assert_eq!(h2.instances, 3);
+    assert_eq!(h2.complete, 1);
+    assert_eq!(h2.right_censored, 1);
+    // (2 + 3 + 1) / 2
+    assert_eq!(h2.mean_lifetime, Some(3.0));
+    assert_eq!(h2.mean_complete_lifetime, Some(3.0));
+
+    // tracking in two parts
+    let mut part1 = SpeciesLifetimes::default();
+    let mut part2 = SpeciesLifetimes::default();
+    for (i, mol) in mols.iter().enumerate() {
+        if i < 3 {
+            part1.add_frame(i, mol);
+        } else {
+            part2.add_frame(i, mol);
+        }
+    }
+    part1.merge(part2);
+    assert_eq!(part1.instances(), lifetimes.instances());
+
+    // statistics are kept for instances taken out
+    let distribution = lifetimes.distribution();
+    let taken = lifetimes.take_destroyed();
+    assert_eq!(taken.len(), 4);
+    assert_eq!(taken[0].1, [true, false]);
+    assert_eq!(lifetimes.instances().len(), 2);
+    assert_eq!(lifetimes.distribution(), distribution);
+    assert_eq!(format!("{:?}", lifetimes.summary()), format!("{summary:?}"));
+}
+// 71d0e4b8 ends here
@@ -0,0 +1,344 @@
+// [[file:../../trajectory.note::3e1f9b62][3e1f9b62]]
+use super::io::Reaction;
+use crate::common::*;
+
+use indexmap::IndexMap;
+// 3e1f9b62 ends here
+
+// [[file:../../trajectory.note::8a4d07c5][8a4d07c5]]
+/// A chemical species in reaction network, identified by its canonical
+/// identifier as in unique reactions summary.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpeciesNode {
+    pub id: String,
+    pub canonical_id: String,
+    pub formula: String,
+    /// The number of times produced in reactions
+    pub produced: usize,
+    /// The number of times consumed in reactions
+    pub consumed: usize,
+    /// The global frame this species first involved in a reaction
+    pub first_frame: Option<usize>,
+}
+
+/// A unique reaction in reaction network, identified by canonical
+/// identifiers of reactants and products.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReactionNode {
+    pub id: String,
+    pub equation: String,
+    pub reactants: Vec<String>,
+    pub products: Vec<String>,
+    /// The number of reaction events
+    pub count: usize,
+    /// The global frame this reaction first occurred
+    pub first_frame: Option<usize>,
+    /// The number of events in this direction minus events in reverse
+    /// direction
+    pub net_flux: isize,
+}
+
+/// A directed edge from reactant species to reaction, or from reaction
+/// to product species.
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkEdge {
+    pub source: String,
+    pub target: String,
+    /// The stoichiometric coefficient of the species in the reaction
+    pub stoichiometry: usize,
+}
+
+/// Bipartite species-reaction graph aggregated from `Reaction` records.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReactionNetwork {
+    species: IndexMap<String, SpeciesNode>,
+    // key: sorted canonical identifiers of reactants and products
+    #[serde(with = "indexmap::serde_seq")]
+    reactions: IndexMap<[Vec<String>; 2], ReactionNode>,
+}
+
+fn update_first_frame(first_frame: &mut Option<usize>, frame: Option<usize>) {
+    if let Some(frame) = frame {
+        *first_frame = Some(first_frame.map_or(frame, |f| f.min(frame)));
+    }
+}
+// 8a4d07c5 ends here
+
+// [[file:../../trajectory.note::c29e7f10][c29e7f10]]
+impl ReactionNetwork {
+    /// Build reaction network from `reactions`.
+    pub fn from_reactions<'a>(reactions: impl IntoIterator<Item = &'a Reaction>) -> Self {
+        let mut network = Self::default();
+        for reaction in reactions {
+            network.add_reaction(reaction);
+        }
+        network
+    }
+
+    /// Add one `reaction` event into the network. Reactions without
+    /// reactants or products will be ignored.
+    pub fn add_reaction(&mut self, reaction: &Reaction) {
+        if reaction.reactants_canonical_ids.is_empty() || reaction.products_canonical_ids.is_empty() {
+            return;
+        }
+        let frame = reaction.global_frame.parse().ok();
+
+        let reactants = self.add_species(&reaction.reactants_canonical_ids, &reaction.reactants_formulas, frame, false);
+        let products = self.add_species(&reaction.products_canonical_ids, &reaction.products_formulas, frame, true);
+
+        let n = self.reactions.len();
+        let node = self.reactions.entry([reactants, products]).or_insert_with_key(|[reactants, products]| {
+            let equation = format!("{} => {}", reactants.join(" + "), products.join(" + "));
+            ReactionNode {
+                id: format!("R{n}"),
+                equation,
+                reactants: reactants.clone(),
+                products: products.clone(),
+                count: 0,
+                first_frame: None,
+                net_flux: 0,
+            }
+        });
+        node.count += 1;
+        update_first_frame(&mut node.first_frame, frame);
+    }
+
+    /// Update species nodes, and return the sorted canonical identifiers.
+    fn add_species(&mut self, ids: &[String], formulas: &[String], frame: Option<usize>, produced: bool) -> Vec<String> {
+        for (i, x) in ids.iter().enumerate() {
+            let n = self.species.len();
+            let node = self.species.entry(x.to_owned()).or_insert_with(|| SpeciesNode {
+                id: format!("S{n}"),
+                canonical_id: x.to_owned(),
+                formula: formulas.get(i).cloned().unwrap_or_default(),
+                produced: 0,
+                consumed: 0,
+                first_frame: None,
+            });
+            if produced {
+                node.produced += 1;
+            } else {
+                node.consumed += 1;
+            }
+            update_first_frame(&mut node.first_frame, frame);
+        }
+        ids.iter().cloned().sorted().collect()
+    }
+
+    /// Return all species in the network.
+    pub fn species(&self) -> impl Iterator<Item = &SpeciesNode> {
+        self.species.values()
+    }
+
+    /// Return all unique reactions in the network with updated net flux.
+    pub fn reactions(&self) -> impl Iterator<Item = ReactionNode> + '_ {
+        self.reactions.iter().map(|([reactants, products], node)| {
+            let key = [products.clone(), reactants.clone()];
+            let reverse_count = self.reactions.get(&key).map_or(0, |x| x.count);
+            let mut node = node.clone();
+            node.net_flux = node.count as isize - reverse_count as isize;
+            // show chemical formulas instead of canonical identifiers
+            let formulas = |ids: &[String]| ids.iter().map(|x| self.species[x].formula.as_str()).collect_vec();
+            node.equation = super::format_equation(&formulas(reactants), &formulas(products));
+            node
+        })
+    }
+
+    /// Return edges between species nodes and reaction nodes.
+    pub fn edges(&self) -> Vec<NetworkEdge> {
+        let mut edges = vec![];
+        for ([reactants, products], node) in self.reactions.iter() {
+            for (x, group) in &reactants.iter().group_by(|x| *x) {
+                edges.push(NetworkEdge {
+                    source: self.species[x].id.clone(),
+                    target: node.id.clone(),
+                    stoichiometry: group.count(),
+                });
+            }
+            for (x, group) in &products.iter().group_by(|x| *x) {
+                edges.push(NetworkEdge {
+                    source: node.id.clone(),
+                    target: self.species[x].id.clone(),
+                    stoichiometry: group.count(),
+                });
+            }
+        }
+        edges
+    }
+}
+// c29e7f10 ends here
+
+// [[file:../../trajectory.note::5b7a6d13][5b7a6d13]]
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl ReactionNetwork {
+    /// Return the network in JSON format.
+    pub fn to_json(&self) -> Result<String> {
+        #[derive(Serialize)]
+        struct Network<'a> {
+            species: Vec<&'a SpeciesNode>,
+            reactions: Vec<ReactionNode>,
+            edges: Vec<NetworkEdge>,
+        }
+
+        let network = Network {
+            species: self.species().collect(),
+            reactions: self.reactions().collect(),
+            edges: self.edges(),
+        };
+        let s = serde_json::to_string_pretty(&network)?;
+        Ok(s)
+    }
+
+    /// Return the network in Graphviz DOT format.
+    pub fn to_dot(&self) -> String {
+        let mut s = String::new();
+        writeln!(s, "digraph reaction_network {{").unwrap();
+        for x in self.species() {
+            writeln!(
+                s,
+                "  {} [shape=ellipse, label=\"{}\", produced={}, consumed={}];",
+                x.id,
+                dot_escape(&x.formula),
+                x.produced,
+                x.consumed
+            )
+            .unwrap();
+        }
+        for x in self.reactions() {
+            writeln!(
+                s,
+                "  {} [shape=box, label=\"{}\", count={}, net_flux={}];",
+                x.id,
+                dot_escape(&x.equation),
+                x.count,
+                x.net_flux
+            )
+            .unwrap();
+        }
+        for x in self.edges() {
+            writeln!(s, "  {} -> {} [label=\"{}\"];", x.source, x.target, x.stoichiometry).unwrap();
+        }
+        writeln!(s, "}}").unwrap();
+        s
+    }
+
+    /// Return the network in GraphML format.
+    pub fn to_graphml(&self) -> String {
+        let mut s = String::new();
+        writeln!(s, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+        writeln!(s, r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#).unwrap();
+        let keys = [
+            ("kind", "node", "string"),
+            ("label", "node", "string"),
+            ("canonical_id", "node", "string"),
+            ("produced", "node", "int"),
+            ("consumed", "node", "int"),
+            ("count", "node", "int"),
+            ("net_flux", "node", "int"),
+            ("first_frame", "node", "int"),
+            ("stoichiometry", "edge", "int"),
+        ];
+        for (name, domain, type_) in keys {
+            writeln!(
+                s,
+                r#"  <key id="{name}" for="{domain}" attr.name="{name}" attr.type="{type_}"/>"#
+            )
+            .unwrap();
+        }
+        writeln!(s, r#"  <graph id="reaction_network" edgedefault="directed">"#).unwrap();
+        let data = |s: &mut String, key: &str, value: &str| {
+            writeln!(s, r#"      <data key="{key}">{}</data>"#, xml_escape(value)).unwrap();
+        };
+        for x in self.species() {
+            writeln!(s, r#"    <node id="{}">"#, x.id).unwrap();
+            data(&mut s, "kind", "species");
+            data(&mut s, "label", &x.formula);
+            data(&mut s, "canonical_id", &x.canonical_id);
+            data(&mut s, "produced", &x.produced.to_string());
+            data(&mut s, "consumed", &x.consumed.to_string());
+            if let Some(frame) = x.first_frame {
+                data(&mut s, "first_frame", &frame.to_string());
+            }
+            writeln!(s, "    </node>").unwrap();
+        }
+        for x in self.reactions() {
+            writeln!(s, r#"    <node id="{}">"#, x.id).unwrap();
+            data(&mut s, "kind", "reaction");
+            data(&mut s, "label", &x.equation);
+            data(&mut s, "count", &x.count.to_string());
+            data(&mut s, "net_flux", &x.net_flux.to_string());
+            if let Some(frame) = x.first_frame {
+                data(&mut s, "first_frame", &frame.to_string());
+            }
+            writeln!(s, "    </node>").unwrap();
+        }
+        for x in self.edges() {
+            writeln!(s, r#"    <edge source="{}" target="{}">"#, x.source, x.target).unwrap();
+            data(&mut s, "stoichiometry", &x.stoichiometry.to_string());
+            writeln!(s, "    </edge>").unwrap();
+        }
+        writeln!(s, "  </graph>").unwrap();
+        writeln!(s, "</graphml>").unwrap();
+        s
+    }
+
+    /// Write the network in GraphML, DOT and JSON formats, with file
+    /// extension appended to `prefix`.
+    pub fn write_files(&self, prefix: &Path) -> Result<()> {
+        let prefix = prefix.display();
+        gut::fs::write_to_file(format!("{prefix}.graphml"), &self.to_graphml())?;
+        gut::fs::write_to_file(format!("{prefix}.dot"), &self.to_dot())?;
+        gut::fs::write_to_file(format!("{prefix}.json"), &self.to_json()?)?;
+        Ok(())
+    }
+}
+// 5b7a6d13 ends here
+
+// [[file:../../trajectory.note::0f6c2e84][0f6c2e84]]
+#[test]
+fn test_reaction_network() -> Result<()> {
+    use super::io::test_reaction as reaction;
+
+    // the same species in another conformation with different
+    // fingerprint
+    let mut conformer = reaction(12, &["h", "h"], &["h2"]);
+    conformer.products_fingerprints = vec!["h2'".into()];
+    let reactions = vec![
+        reaction(5, &["h", "h"], &["h2"]),
+        reaction(9, &["h2"], &["h", "h"]),
+        conformer,
+    ];
+    let network = ReactionNetwork::from_reactions(&reactions);
+    assert_eq!(network.species().count(), 2);
+    let nodes: Vec<_> = network.reactions().collect();
+    assert_eq!(nodes.len(), 2);
+    assert_eq!(nodes[0].equation, "2 H => H2");
+    assert_eq!(nodes[0].count, 2);
+    assert_eq!(nodes[0].first_frame, Some(5));
+    assert_eq!(nodes[0].net_flux, 1);
+    assert_eq!(nodes[1].net_flux, -1);
+
+    let edges = network.edges();
+    assert_eq!(edges.len(), 4);
+    assert_eq!(edges[0].stoichiometry, 2);
+
+    assert!(network.to_graphml().contains("<graphml"));
+    let dot = network.to_dot();
+    assert!(dot.starts_with("digraph"));
+    assert!(dot.contains(r#"label="2 H => H2""#));
+    assert_eq!(dot_escape(r#"A"B\C"#), r#"A\"B\\C"#);
+    assert!(network.to_json()?.contains("\"net_flux\": 1"));
+
+    Ok(())
+}
+// 0f6c2e84 ends here
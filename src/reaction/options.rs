@@ -1,3 +1,10 @@
+// [[file:../../trajectory.note::2f9c61e0][2f9c61e0]]
+use super::bonding::BondingCriterion;
+use super::filter::ReactionFilter;
+use super::io::ReactionFormat;
+use crate::common::*;
+// 2f9c61e0 ends here
+
 // [[file:../../trajectory.note::65c83c1c][65c83c1c]]
 #[derive(Debug, Clone)]
 /// User options for reaction analysis
@@ -12,6 +19,75 @@ pub struct ReactionOptions {
     pub chunk_size: usize,
     /// Read lattice from xyz title in extxyz format (Lattice=*)
     pub read_lattice_extxyz: bool,
+    /// Write reaction network in GraphML, DOT and JSON formats.
+    pub write_reaction_network: bool,
+    /// Write species population of each frame into this file (in CSV
+    /// format if file extension is csv, otherwise in Parquet format).
+    pub species_population_file: Option<PathBuf>,
+    /// Write found reactions into this file. The default is
+    /// `reaction.pq` in the same dir as trajectory file. Other output
+    /// files and directories without paths set, such as checkpoint and
+    /// reaction species, will be written in the same dir as this file.
+    pub reaction_file: Option<PathBuf>,
+    /// The format for writing reactions. If not set, the format will be
+    /// guessed from file extension of `reaction_file`.
+    pub reaction_format: Option<ReactionFormat>,
+    /// The MD timestep in fs. If set, rate constants of reactions will
+    /// be estimated, and the time of frames will be computed from MD
+    /// steps if not found in frame titles.
+    pub timestep: Option<f64>,
+    /// The volume of simulation box in Å^3 for second-order rate
+    /// constants. The lattice volume will be used if not set.
+    pub volume: Option<f64>,
+    /// The criterion for determining chemical bonds in each frame.
+    pub bonding: BondingCriterion,
+    /// Read bonds from ReaxFF bonds file written by LAMMPS `fix
+    /// reaxff/bonds` instead of guessing from geometry.
+    pub reaxff_bonds_file: Option<PathBuf>,
+    /// The bond order cutoff for bonds in ReaxFF bonds file.
+    pub reaxff_bond_order_cutoff: f64,
+    /// Scale factors of covalent radii for forming and breaking bonds
+    /// in hysteresis bonding. If set, bonds are determined frame by
+    /// frame in one pass, and noise removing is not required.
+    pub bond_hysteresis: Option<[f64; 2]>,
+    /// Element symbols for atom types in LAMMPS dump file: the first
+    /// one for type 1, the second one for type 2, and so on.
+    pub lammps_type_elements: Vec<String>,
+    /// The number of chunks to be analyzed at once in parallel. The
+    /// memory usage will increase accordingly.
+    pub parallel_chunks: usize,
+    /// Write checkpoint after processing each batch of chunks, which
+    /// can be used for resuming.
+    pub write_checkpoint: bool,
+    /// Resume from the checkpoint written before.
+    pub resume: bool,
+    /// Write unique reactions into this file (in CSV format if file
+    /// extension is csv, in Markdown format if md, otherwise in Parquet
+    /// format).
+    pub reaction_summary_file: Option<PathBuf>,
+    /// Sort unique reactions by count in descending order.
+    pub summary_sort_by_count: bool,
+    /// Track the species history of these atoms (in atom serial numbers).
+    pub tracked_atoms: Vec<usize>,
+    /// Write species history of tracked atoms into this file in JSON
+    /// format.
+    pub atom_fates_file: Option<PathBuf>,
+    /// Write lifetime statistics of species into this file in CSV
+    /// format, with lifetime distribution and fragment instances in
+    /// files next to it.
+    pub species_lifetimes_file: Option<PathBuf>,
+    /// Only reactions selected by this filter will be recorded, and
+    /// written into species files.
+    pub reaction_filter: ReactionFilter,
+    /// Write this number of frames before and after each reaction event
+    /// in `reactive-frames` directory for transition state searches.
+    pub reaction_window: Option<usize>,
+    /// The radius in Å around reacting atoms for cropping frames in
+    /// reaction window.
+    pub reaction_window_shell: f64,
+    /// Center reacting atoms in periodic cell when writing reactive
+    /// frames, and keep molecules whole.
+    pub center_reactive_frames: bool,
 }
 
 impl Default for ReactionOptions {
@@ -22,6 +98,41 @@ impl Default for ReactionOptions {
             write_reaction_species: false,
             chunk_size: 150,
             read_lattice_extxyz: true,
+            write_reaction_network: false,
+            species_population_file: None,
+            reaction_file: None,
+            reaction_format: None,
+            timestep: None,
+            volume: None,
+            bonding: BondingCriterion::default(),
+            reaxff_bonds_file: None,
+            reaxff_bond_order_cutoff: 0.3,
+            bond_hysteresis: None,
+            lammps_type_elements: vec![],
+            parallel_chunks: 1,
+            write_checkpoint: false,
+            resume: false,
+            reaction_summary_file: None,
+            summary_sort_by_count: false,
+            tracked_atoms: vec![],
+            atom_fates_file: None,
+            species_lifetimes_file: None,
+            reaction_filter: ReactionFilter::default(),
+            reaction_window: None,
+            reaction_window_shell: 4.0,
+            center_reactive_frames: false,
+        }
+    }
+}
+
+impl ReactionOptions {
+    /// Return the noise event life for noise removing. Return None if
+    /// noise removing is not required (for hysteresis bonding).
+    pub fn noise_removing(&self) -> Option<usize> {
+        if self.bond_hysteresis.is_some() {
+            None
+        } else {
+            Some(self.noise_event_life)
         }
     }
 }
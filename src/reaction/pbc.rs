@@ -0,0 +1,101 @@
+// [[file:../../trajectory.note::6b2e9d41][6b2e9d41]]
+use crate::common::*;
+
+use gchemol::{Lattice, Molecule};
+use vecfx::*;
+// 6b2e9d41 ends here
+
+// [[file:../../trajectory.note::e07a5c38][e07a5c38]]
+/// Rebuild each fragment in `mol` as one connected image under periodic
+/// `lat`: the atoms are moved to their images following the minimum
+/// image bond vectors from the first atom in fragment.
+pub fn unwrap_fragments_with(mol: &mut Molecule, lat: &Lattice) {
+    let mut visited = std::collections::HashSet::new();
+    let numbers = mol.numbers().collect_vec();
+    for a in numbers {
+        if !visited.insert(a) {
+            continue;
+        }
+        // breadth-first search over bonds
+        let mut queue = std::collections::VecDeque::from([a]);
+        while let Some(i) = queue.pop_front() {
+            let pi: Vector3f = mol.get_atom_unchecked(i).position().into();
+            for j in mol.connected(i).collect_vec() {
+                if visited.insert(j) {
+                    let pj: Vector3f = mol.get_atom_unchecked(j).position().into();
+                    let d = lat.apply_mic(pj - pi);
+                    mol.set_position(j, pi + d);
+                    queue.push_back(j);
+                }
+            }
+        }
+    }
+}
+
+/// Rebuild each fragment in periodic `mol` as one connected image. Do
+/// nothing for non-periodic `mol`.
+pub fn unwrap_fragments(mol: &mut Molecule) {
+    if let Some(lat) = mol.lattice {
+        unwrap_fragments_with(mol, &lat);
+    }
+}
+
+/// Translate periodic `mol` to place the center of `atoms` at the cell
+/// center, and move each fragment into the cell as a whole. Do nothing
+/// for non-periodic `mol`.
+pub fn center_atoms_in_cell(mol: &mut Molecule, atoms: &[usize]) {
+    let lat = match mol.lattice {
+        Some(lat) => lat,
+        None => return,
+    };
+    unwrap_fragments_with(mol, &lat);
+    let positions = atoms.iter().filter_map(|&a| mol.get_atom(a)).map(|x| Vector3f::from(x.position())).collect_vec();
+    if positions.is_empty() {
+        return;
+    }
+    // the center of atoms in their nearest images to the first atom
+    let p0 = positions[0];
+    let center = positions.iter().map(|&p| p0 + lat.apply_mic(p - p0)).fold(Vector3f::zeros(), |acc, p| acc + p)
+        / positions.len() as f64;
+    let cell_center = lat.to_cart([0.5; 3]);
+    let disp = cell_center - center;
+
+    for frag in mol.fragmented().collect_vec() {
+        let numbers = frag.numbers().collect_vec();
+        let positions = numbers.iter().map(|&a| Vector3f::from(mol.get_atom_unchecked(a).position()) + disp).collect_vec();
+        let centroid = positions.iter().fold(Vector3f::zeros(), |acc, &p| acc + p) / positions.len() as f64;
+        let shift = lat.wrap(centroid) - centroid;
+        for (a, p) in numbers.into_iter().zip(positions) {
+            mol.set_position(a, p + shift);
+        }
+    }
+}
+// e07a5c38 ends here
+
+// [[file:../../trajectory.note::5f81c2d6][5f81c2d6]]
+#[test]
+fn test_unwrap_fragments() {
+    use gchemol::{Atom, Bond};
+
+    // H2 split across the cell boundary
+    let atoms = [[0.2, 5.0, 5.0], [9.6, 5.0, 5.0], [3.0, 5.0, 5.0]].map(|p| Atom::new("H", p));
+    let mut mol = Molecule::from_atoms(atoms);
+    mol.set_lattice(Lattice::new([[10.0, 0.0, 0.0], [0.0, 10.0, 0.0], [0.0, 0.0, 10.0]]));
+    mol.add_bond(1, 2, Bond::default());
+
+    let mut mol1 = mol.clone();
+    unwrap_fragments(&mut mol1);
+    let [x1, _, _] = mol1.get_atom_unchecked(1).position();
+    let [x2, _, _] = mol1.get_atom_unchecked(2).position();
+    assert_relative_eq!(x1 - x2, 0.6, epsilon = 1e-6);
+
+    center_atoms_in_cell(&mut mol, &[1, 2]);
+    let [x1, _, _] = mol.get_atom_unchecked(1).position();
+    let [x2, _, _] = mol.get_atom_unchecked(2).position();
+    assert_relative_eq!(x1, 5.3, epsilon = 1e-6);
+    assert_relative_eq!(x2, 4.7, epsilon = 1e-6);
+    // moved as a whole into cell
+    let [x3, _, _] = mol.get_atom_unchecked(3).position();
+    assert_relative_eq!(x3, 8.1, epsilon = 1e-6);
+}
+// 5f81c2d6 ends here
@@ -0,0 +1,154 @@
+// [[file:../../trajectory.note::b4f2a8d1][b4f2a8d1]]
+use crate::common::*;
+
+use gchemol::Molecule;
+use gosh_dataset::SimpleParquetFileWriter;
+use std::collections::BTreeMap;
+// b4f2a8d1 ends here
+
+// [[file:../../trajectory.note::6d0c3e95][6d0c3e95]]
+/// The number of fragments of one species in one frame
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SpeciesPopulation {
+    #[serde(rename = "Global frame")]
+    pub global_frame: String,
+    #[serde(rename = "Species")]
+    pub species: String,
+    #[serde(rename = "Count")]
+    pub count: usize,
+}
+
+/// Count fragments in `mol` by their chemical formulas.
+pub fn count_species(mol: &Molecule) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    for frag in mol.fragmented() {
+        *counts.entry(frag.formula()).or_default() += 1;
+    }
+    counts
+}
+
+/// Return species population for each frame in `mols`. The global
+/// frame is taken from the molecule title.
+pub fn get_species_population(mols: &[Molecule]) -> Vec<SpeciesPopulation> {
+    mols.par_iter()
+        .flat_map_iter(|mol| {
+            let global_frame = mol.title();
+            count_species(mol).into_iter().map(move |(species, count)| SpeciesPopulation {
+                global_frame: global_frame.clone(),
+                species,
+                count,
+            })
+        })
+        .collect()
+}
+// 6d0c3e95 ends here
+
+// [[file:../../trajectory.note::e7a19c42][e7a19c42]]
+/// Write species population in Parquet format, or in CSV format if
+/// file extension is `csv`.
+pub enum PopulationWriter {
+    /// Parquet file writer and the number of rows written
+    Parquet(SimpleParquetFileWriter, u64),
+    Csv(std::io::BufWriter<std::fs::File>),
+}
+
+fn is_csv(f: &Path) -> bool {
+    f.extension().map_or(false, |x| x == "csv")
+}
+
+impl PopulationWriter {
+    pub fn new(f: &Path) -> Result<Self> {
+        let writer = if is_csv(f) {
+            let mut w = std::io::BufWriter::new(std::fs::File::create(f)?);
+            writeln!(w, "Global frame,Species,Count")?;
+            Self::Csv(w)
+        } else {
+            Self::Parquet(SimpleParquetFileWriter::new(f), 0)
+        };
+        Ok(writer)
+    }
+
+    /// Reopen file `f` for appending population after `position`
+    /// returned by `Self::position`. Rows written after `position` will
+    /// be discarded.
+    pub fn resume(f: &Path, position: u64) -> Result<Self> {
+        if is_csv(f) {
+            let w = super::io::reopen_for_append(f, position)?;
+            return Ok(Self::Csv(w));
+        }
+        // NOTE: Parquet file cannot be appended, so the rows before
+        // `position` are read back and rewritten
+        let mut population: Vec<SpeciesPopulation> =
+            if position > 0 { super::io::read_parquet_rows(f)? } else { vec![] };
+        ensure!(
+            population.len() as u64 >= position,
+            "found only {} rows of species population in {f:?}, but {position} expected",
+            population.len()
+        );
+        population.truncate(position as usize);
+        let mut writer = Self::new(f)?;
+        writer.write_population(&population)?;
+        Ok(writer)
+    }
+
+    /// Flush population written and return the position for resuming:
+    /// the file size in bytes for CSV, or the number of rows for
+    /// Parquet.
+    pub fn position(&mut self) -> Result<u64> {
+        match self {
+            Self::Parquet(_, nrows) => Ok(*nrows),
+            Self::Csv(w) => {
+                w.flush()?;
+                Ok(w.get_ref().metadata()?.len())
+            }
+        }
+    }
+
+    pub fn write_population(&mut self, population: &[SpeciesPopulation]) -> Result<()> {
+        if population.is_empty() {
+            return Ok(());
+        }
+        match self {
+            Self::Parquet(w, nrows) => {
+                w.write_row_group(population)?;
+                *nrows += population.len() as u64;
+            }
+            Self::Csv(w) => {
+                for x in population {
+                    writeln!(w, "{},{},{}", x.global_frame, x.species, x.count)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn close(self) -> Result<()> {
+        match self {
+            Self::Parquet(w, _) => w.close()?,
+            Self::Csv(mut w) => w.flush()?,
+        }
+        Ok(())
+    }
+}
+// e7a19c42 ends here
+
+// [[file:../../trajectory.note::1a8e5f37][1a8e5f37]]
+#[test]
+fn test_species_population() -> Result<()> {
+    let f = "tests/files/lty.xyz";
+    let mut mols: Vec<_> = gchemol::io::read(f)?.take(2).collect();
+    for (i, mol) in mols.iter_mut().enumerate() {
+        mol.rebond();
+        mol.set_title(format!("{i}"));
+    }
+
+    let counts = count_species(&mols[0]);
+    let n: usize = counts.values().sum();
+    assert_eq!(n, mols[0].nfragments());
+
+    let population = get_species_population(&mols);
+    assert!(population.iter().any(|x| x.global_frame == "1"));
+
+    Ok(())
+}
+// 1a8e5f37 ends here
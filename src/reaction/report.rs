@@ -0,0 +1,195 @@
+// [[file:../../trajectory.note::a37d1f60][a37d1f60]]
+use super::io::Reaction;
+use super::summary::{ReactionSummary, UniqueReaction};
+use crate::common::*;
+
+use std::collections::BTreeMap;
+// a37d1f60 ends here
+
+// [[file:../../trajectory.note::4c9e2b18][4c9e2b18]]
+/// The number of reactions in one time bin
+#[derive(Debug, Clone, Serialize)]
+pub struct ReactionBin {
+    pub start: f64,
+    pub end: f64,
+    pub count: usize,
+}
+
+/// The number of times one species produced and consumed in reactions
+#[derive(Debug, Clone, Serialize)]
+pub struct SpeciesBalance {
+    /// The chemical formula of species
+    pub species: String,
+    pub canonical_id: String,
+    pub produced: usize,
+    pub consumed: usize,
+    /// Produced minus consumed
+    pub net: isize,
+}
+
+/// Overview of reactions found in reaction analysis
+#[derive(Debug, Clone, Serialize)]
+pub struct ReactionReport {
+    pub total_reactions: usize,
+    pub low_confidence_reactions: usize,
+    /// The most frequent unique reactions
+    pub top_reactions: Vec<UniqueReaction>,
+    /// The unit of time bins: "fs" if reaction time is available,
+    /// otherwise "frame"
+    pub time_unit: String,
+    pub histogram: Vec<ReactionBin>,
+    /// Species in descending order of net production
+    pub most_produced: Vec<SpeciesBalance>,
+    /// Species in descending order of net consumption
+    pub most_consumed: Vec<SpeciesBalance>,
+}
+
+impl ReactionReport {
+    /// Create report for `reactions`, with `top` unique reactions and
+    /// species, and reactions counted in `nbins` time bins.
+    pub fn new(reactions: &[Reaction], top: usize, nbins: usize) -> Self {
+        let summary = ReactionSummary::from_reactions(reactions);
+        let top_reactions = summary.unique_reactions(true).into_iter().take(top).collect();
+
+        // use simulation time if recorded for all reactions
+        let times: Option<Vec<f64>> = reactions.iter().map(|r| r.time_fs).collect();
+        let (time_unit, times) = match times {
+            Some(times) if !times.is_empty() => ("fs", times),
+            _ => ("frame", reactions.iter().map(|r| r.frame as f64).collect()),
+        };
+        let histogram = get_histogram(&times, nbins);
+
+        // species identified by canonical identifiers as in unique
+        // reactions
+        let mut balance: BTreeMap<&str, (&str, [usize; 2])> = BTreeMap::new();
+        for r in reactions {
+            for (x, formula) in r.products_canonical_ids.iter().zip(&r.products_formulas) {
+                balance.entry(x).or_insert((formula, [0; 2])).1[0] += 1;
+            }
+            for (x, formula) in r.reactants_canonical_ids.iter().zip(&r.reactants_formulas) {
+                balance.entry(x).or_insert((formula, [0; 2])).1[1] += 1;
+            }
+        }
+        let balance = balance
+            .into_iter()
+            .map(|(canonical_id, (species, [produced, consumed]))| SpeciesBalance {
+                species: species.to_string(),
+                canonical_id: canonical_id.to_string(),
+                produced,
+                consumed,
+                net: produced as isize - consumed as isize,
+            })
+            .collect_vec();
+        let most_produced = balance.iter().filter(|x| x.net > 0).sorted_by_key(|x| -x.net).take(top).cloned().collect();
+        let most_consumed = balance.iter().filter(|x| x.net < 0).sorted_by_key(|x| x.net).take(top).cloned().collect();
+
+        Self {
+            total_reactions: reactions.len(),
+            low_confidence_reactions: reactions.iter().filter(|r| r.low_confidence).count(),
+            top_reactions,
+            time_unit: time_unit.into(),
+            histogram,
+            most_produced,
+            most_consumed,
+        }
+    }
+
+    /// Return the report in plain text for terminal.
+    pub fn to_text(&self) -> String {
+        let mut s = String::new();
+        writeln!(s, "Total reactions: {} ({} in low confidence)", self.total_reactions, self.low_confidence_reactions).unwrap();
+
+        writeln!(s, "\nTop reactions:").unwrap();
+        for x in &self.top_reactions {
+            writeln!(s, "{:>8}  {}", x.count, x.equation).unwrap();
+        }
+
+        writeln!(s, "\nReactions per time bin ({}):", self.time_unit).unwrap();
+        let max_count = self.histogram.iter().map(|x| x.count).max().unwrap_or(0).max(1);
+        for x in &self.histogram {
+            let bar = "#".repeat(x.count * 50 / max_count);
+            writeln!(s, "{:>12.1} - {:<12.1} {:>8} {bar}", x.start, x.end, x.count).unwrap();
+        }
+
+        for (title, species) in [("Most produced", &self.most_produced), ("Most consumed", &self.most_consumed)] {
+            writeln!(s, "\n{title} species (net, produced, consumed):").unwrap();
+            for x in species {
+                writeln!(s, "{:>8} {:>8} {:>8}  {}", x.net, x.produced, x.consumed, x.species).unwrap();
+            }
+        }
+        s
+    }
+
+    /// Write the report into file `f` in JSON format.
+    pub fn write_json(&self, f: &Path) -> Result<()> {
+        let s = serde_json::to_string_pretty(self)?;
+        gut::fs::write_to_file(f, &s)?;
+        Ok(())
+    }
+}
+
+/// Count `values` in `nbins` bins of equal width.
+fn get_histogram(values: &[f64], nbins: usize) -> Vec<ReactionBin> {
+    if values.is_empty() || nbins == 0 {
+        return vec![];
+    }
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    // avoid zero width when all values are the same
+    let width = ((max - min) / nbins as f64).max(f64::EPSILON);
+    let mut bins = (0..nbins)
+        .map(|i| ReactionBin {
+            start: min + i as f64 * width,
+            end: min + (i + 1) as f64 * width,
+            count: 0,
+        })
+        .collect_vec();
+    for &x in values {
+        let i = (((x - min) / width) as usize).min(nbins - 1);
+        bins[i].count += 1;
+    }
+    bins
+}
+// 4c9e2b18 ends here
+
+// [[file:../../trajectory.note::d518e7c2][d518e7c2]]
+#[test]
+fn test_reaction_report() -> Result<()> {
+    use super::io::test_reaction as reaction;
+    use super::io::{read_reactions, ReactionFormat, ReactionWriter};
+
+    let reactions = vec![
+        reaction(0, &["h", "h"], &["h2"]),
+        reaction(9, &["h2"], &["h", "h"]),
+        reaction(12, &["h", "h"], &["h2"]),
+        reaction(15, &["h", "h"], &["h2"]),
+        reaction(20, &["h2"], &["h", "h"]),
+    ];
+
+    // round trip in parquet format
+    let tdir = gut::fs::tempfile::tempdir()?;
+    let f = tdir.path().join("reaction.pq");
+    let mut writer = ReactionWriter::with_format(&f, ReactionFormat::Parquet)?;
+    writer.write_reactions(&reactions[..2])?;
+    writer.write_reactions(&reactions[2..])?;
+    writer.close()?;
+    let reactions = read_reactions(&f)?;
+    assert_eq!(reactions.len(), 5);
+    assert_eq!(reactions[2].reactants_formulas, ["H", "H"]);
+
+    let report = ReactionReport::new(&reactions, 1, 2);
+    assert_eq!(report.total_reactions, 5);
+    assert_eq!(report.top_reactions.len(), 1);
+    assert_eq!(report.top_reactions[0].equation, "2 H => H2");
+    assert_eq!(report.time_unit, "frame");
+    assert_eq!(report.histogram.iter().map(|x| x.count).collect_vec(), [2, 3]);
+    assert_eq!(report.most_produced[0].species, "H2");
+    assert_eq!(report.most_produced[0].canonical_id, "h2");
+    assert_eq!(report.most_produced[0].net, 1);
+    assert_eq!(report.most_consumed[0].species, "H");
+    assert_eq!(report.most_consumed[0].net, -2);
+    assert!(report.to_text().contains("2 H => H2"));
+
+    Ok(())
+}
+// d518e7c2 ends here
@@ -0,0 +1,210 @@
+// [[file:../../trajectory.note::c4e8d217][c4e8d217]]
+use crate::common::*;
+
+use gchemol::Molecule;
+// c4e8d217 ends here
+
+// [[file:../../trajectory.note::0f6b93ea][0f6b93ea]]
+/// Molecular graph with atoms labeled by element symbols, indexed from
+/// 0 in canonical order.
+struct LabeledGraph {
+    symbols: Vec<String>,
+    neighbors: Vec<Vec<usize>>,
+}
+
+impl LabeledGraph {
+    /// Construct graph of `mol` in canonical atom order from the
+    /// nauty-backed canonical labeling in spdkit. The atoms are grouped
+    /// by element symbols, then ordered by canonical labels.
+    fn canonical(mol: &Molecule) -> Self {
+        // for Molecule.reorder_cannonically method
+        use spdkit::prelude::*;
+
+        let mut mol = mol.clone();
+        if mol.natoms() > 0 {
+            // atoms will be renumbered from 1 in canonical order
+            mol.reorder_cannonically();
+        }
+        let numbers = mol.numbers().sorted_by_key(|&n| (mol.get_atom_unchecked(n).symbol(), n)).collect_vec();
+        let index: HashMap<usize, usize> = numbers.iter().enumerate().map(|(i, &n)| (n, i)).collect();
+        let symbols = numbers.iter().map(|&n| mol.get_atom_unchecked(n).symbol().to_string()).collect();
+        let mut neighbors = vec![vec![]; numbers.len()];
+        for (u, v, _) in mol.bonds() {
+            let (i, j) = (index[&u], index[&v]);
+            neighbors[i].push(j);
+            neighbors[j].push(i);
+        }
+        for x in neighbors.iter_mut() {
+            x.sort();
+        }
+        Self { symbols, neighbors }
+    }
+
+    /// Return bonds in canonical indices.
+    fn edges(&self) -> Vec<[usize; 2]> {
+        let edges = self.neighbors.iter().enumerate().flat_map(|(i, x)| x.iter().map(move |&j| [i, j]));
+        edges.filter(|[i, j]| i < j).sorted().collect()
+    }
+}
+// 0f6b93ea ends here
+
+// [[file:../../trajectory.note::a8d25f41][a8d25f41]]
+/// Canonical representations of a species, which are independent of
+/// atom order and stable across runs.
+///
+/// NOTE: bond types and geometry are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct CanonicalSpecies {
+    /// Element symbols in canonical order and bonds between them in
+    /// canonical indices, such as "C,H,H,H,H;0-1,0-2,0-3,0-4" for CH4.
+    pub id: String,
+    /// SMILES-like line notation in canonical atom order. All atoms
+    /// including hydrogens are written explicitly, and bond orders are
+    /// not written. Element symbols other than B, C, N, O, P, S, F, Cl,
+    /// Br, I and H are written in brackets.
+    pub smiles: String,
+}
+
+impl CanonicalSpecies {
+    /// Canonicalize `mol` once for both representations.
+    pub fn new(mol: &Molecule) -> Self {
+        let graph = LabeledGraph::canonical(mol);
+        let symbols = graph.symbols.join(",");
+        let edges = graph.edges().iter().map(|[i, j]| format!("{i}-{j}")).join(",");
+        Self {
+            id: format!("{symbols};{edges}"),
+            smiles: smiles_like(&graph),
+        }
+    }
+}
+
+fn smiles_like(graph: &LabeledGraph) -> String {
+    let n = graph.symbols.len();
+
+    // find ring closure bonds in depth-first search
+    let mut visited = vec![false; n];
+    let mut children = vec![vec![]; n];
+    let mut ring_bonds: Vec<[usize; 2]> = vec![];
+    fn dfs(
+        i: usize,
+        parent: Option<usize>,
+        neighbors: &[Vec<usize>],
+        visited: &mut [bool],
+        children: &mut [Vec<usize>],
+        ring_bonds: &mut Vec<[usize; 2]>,
+    ) {
+        visited[i] = true;
+        for &j in &neighbors[i] {
+            if Some(j) == parent {
+                continue;
+            }
+            if visited[j] {
+                // each ring bond is found twice
+                if !ring_bonds.contains(&[j, i]) {
+                    ring_bonds.push([i, j]);
+                }
+            } else {
+                children[i].push(j);
+                dfs(j, Some(i), neighbors, visited, children, ring_bonds);
+            }
+        }
+    }
+
+    // write atoms with ring closure digits and branches
+    fn write(
+        i: usize,
+        symbols: &[String],
+        children: &[Vec<usize>],
+        ring_bonds: &[[usize; 2]],
+        digits: &mut HashMap<[usize; 2], usize>,
+        s: &mut String,
+    ) {
+        let sym = symbols[i].as_str();
+        match sym {
+            "B" | "C" | "N" | "O" | "P" | "S" | "F" | "Cl" | "Br" | "I" | "H" => s.push_str(sym),
+            _ => s.push_str(&format!("[{sym}]")),
+        }
+        for bond in ring_bonds.iter().filter(|b| b.contains(&i)) {
+            let d = match digits.remove(bond) {
+                // close ring
+                Some(d) => d,
+                // open ring with the smallest free digit
+                None => {
+                    let d = (1..).find(|d| !digits.values().any(|x| x == d)).unwrap();
+                    digits.insert(*bond, d);
+                    d
+                }
+            };
+            if d < 10 {
+                s.push_str(&d.to_string());
+            } else {
+                s.push_str(&format!("%{d}"));
+            }
+        }
+        let nchildren = children[i].len();
+        for (k, &j) in children[i].iter().enumerate() {
+            if k + 1 < nchildren {
+                s.push('(');
+                write(j, symbols, children, ring_bonds, digits, s);
+                s.push(')');
+            } else {
+                write(j, symbols, children, ring_bonds, digits, s);
+            }
+        }
+    }
+
+    let mut components = vec![];
+    for i in 0..n {
+        if !visited[i] {
+            dfs(i, None, &graph.neighbors, &mut visited, &mut children, &mut ring_bonds);
+            let mut s = String::new();
+            write(i, &graph.symbols, &children, &ring_bonds, &mut HashMap::new(), &mut s);
+            components.push(s);
+        }
+    }
+    components.join(".")
+}
+// a8d25f41 ends here
+
+// [[file:../../trajectory.note::6e39b0c5][6e39b0c5]]
+#[test]
+fn test_canonical_species() {
+    use gchemol::{Atom, Bond};
+
+    let molecule = |symbols: &[&str], bonds: &[[usize; 2]]| {
+        let atoms = symbols.iter().map(|&x| Atom::new(x, [0.0; 3]));
+        let mut mol = Molecule::from_atoms(atoms);
+        for &[u, v] in bonds {
+            mol.add_bond(u, v, Bond::default());
+        }
+        mol
+    };
+
+    // methane in different atom orders
+    let ch4a = molecule(&["C", "H", "H", "H", "H"], &[[1, 2], [1, 3], [1, 4], [1, 5]]);
+    let ch4b = molecule(&["H", "H", "C", "H", "H"], &[[3, 1], [3, 2], [3, 4], [3, 5]]);
+    let (ch4a, ch4b) = (CanonicalSpecies::new(&ch4a), CanonicalSpecies::new(&ch4b));
+    assert_eq!(ch4a.id, ch4b.id);
+    assert_eq!(ch4a.id, "C,H,H,H,H;0-1,0-2,0-3,0-4");
+    assert_eq!(ch4a.smiles, "C(H)(H)(H)H");
+
+    // ethanol and dimethyl ether
+    let ethanol = CanonicalSpecies::new(&molecule(&["C", "C", "O"], &[[1, 2], [2, 3]]));
+    let ether = CanonicalSpecies::new(&molecule(&["C", "O", "C"], &[[1, 2], [2, 3]]));
+    let ethanol2 = CanonicalSpecies::new(&molecule(&["O", "C", "C"], &[[1, 2], [2, 3]]));
+    assert_ne!(ethanol.id, ether.id);
+    assert_eq!(ethanol.id, ethanol2.id);
+    assert_eq!(ethanol.smiles, ethanol2.smiles);
+
+    // ring in different atom orders
+    let ring1 = CanonicalSpecies::new(&molecule(&["C", "C", "C", "O"], &[[1, 2], [2, 3], [3, 4], [4, 1]]));
+    let ring2 = CanonicalSpecies::new(&molecule(&["C", "O", "C", "C"], &[[1, 2], [2, 3], [3, 4], [4, 1]]));
+    assert_eq!(ring1.id, ring2.id);
+    assert_eq!(ring1.smiles, ring2.smiles);
+    assert!(ring1.smiles.contains('1'));
+
+    let pt = CanonicalSpecies::new(&molecule(&["Pt"], &[]));
+    assert_eq!(pt.id, "Pt;");
+    assert_eq!(pt.smiles, "[Pt]");
+}
+// 6e39b0c5 ends here
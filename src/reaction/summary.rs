@@ -0,0 +1,193 @@
+// [[file:../../trajectory.note::7d3a91c5][7d3a91c5]]
+use super::io::Reaction;
+use crate::common::*;
+
+use gosh_dataset::SimpleParquetFileWriter;
+use indexmap::IndexMap;
+// 7d3a91c5 ends here
+
+// [[file:../../trajectory.note::e2b64f08][e2b64f08]]
+/// One unique reaction summarized from reaction events.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UniqueReaction {
+    #[serde(rename = "Reaction")]
+    pub equation: String,
+    /// The number of reaction events
+    #[serde(rename = "Count")]
+    pub count: usize,
+    /// The global frame this reaction first occurred
+    #[serde(rename = "First frame")]
+    pub first_frame: Option<usize>,
+    /// The global frame this reaction last occurred
+    #[serde(rename = "Last frame")]
+    pub last_frame: Option<usize>,
+    /// The number of events in reverse direction
+    #[serde(rename = "Reverse count")]
+    pub reverse_count: usize,
+    /// The number of events in this direction minus events in reverse
+    /// direction
+    #[serde(rename = "Net count")]
+    pub net_count: isize,
+    /// Canonical identifiers of reactants
+    #[serde(rename = "Reactants")]
+    pub reactants: Vec<String>,
+    /// Canonical identifiers of products
+    #[serde(rename = "Products")]
+    pub products: Vec<String>,
+}
+
+/// Unique reactions deduplicated by canonical identifiers of reactants
+/// and products, and direction.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReactionSummary {
+    // key: sorted canonical identifiers of reactants and products
+    #[serde(with = "indexmap::serde_seq")]
+    reactions: IndexMap<[Vec<String>; 2], UniqueReaction>,
+}
+
+impl ReactionSummary {
+    /// Summarize unique reactions from `reactions`.
+    pub fn from_reactions<'a>(reactions: impl IntoIterator<Item = &'a Reaction>) -> Self {
+        let mut summary = Self::default();
+        for reaction in reactions {
+            summary.add_reaction(reaction);
+        }
+        summary
+    }
+
+    /// Add one `reaction` event. Reactions without reactants or
+    /// products will be ignored.
+    pub fn add_reaction(&mut self, reaction: &Reaction) {
+        if reaction.reactants_canonical_ids.is_empty() || reaction.products_canonical_ids.is_empty() {
+            return;
+        }
+        let sorted = |ids: &[String], formulas: &[String]| -> (Vec<String>, Vec<String>) {
+            ids.iter().cloned().zip(formulas.iter().cloned()).sorted().unzip()
+        };
+        let (rids, rformulas) = sorted(&reaction.reactants_canonical_ids, &reaction.reactants_formulas);
+        let (pids, pformulas) = sorted(&reaction.products_canonical_ids, &reaction.products_formulas);
+        let frame = reaction.global_frame.parse().ok();
+        let entry = self
+            .reactions
+            .entry([rids, pids])
+            .or_insert_with_key(|[reactants, products]| UniqueReaction {
+                equation: super::format_equation(&rformulas, &pformulas),
+                count: 0,
+                first_frame: None,
+                last_frame: None,
+                reverse_count: 0,
+                net_count: 0,
+                reactants: reactants.clone(),
+                products: products.clone(),
+            });
+        entry.count += 1;
+        if let Some(frame) = frame {
+            entry.first_frame = Some(entry.first_frame.map_or(frame, |f| f.min(frame)));
+            entry.last_frame = Some(entry.last_frame.map_or(frame, |f| f.max(frame)));
+        }
+    }
+
+    /// Return all unique reactions with reverse and net counts, in order
+    /// of first occurrence, or in descending order of count if
+    /// `sort_by_count` is true.
+    pub fn unique_reactions(&self, sort_by_count: bool) -> Vec<UniqueReaction> {
+        let mut reactions = self
+            .reactions
+            .iter()
+            .map(|([reactants, products], x)| {
+                let key = [products.clone(), reactants.clone()];
+                let mut x = x.clone();
+                x.reverse_count = self.reactions.get(&key).map_or(0, |r| r.count);
+                x.net_count = x.count as isize - x.reverse_count as isize;
+                x
+            })
+            .collect_vec();
+        if sort_by_count {
+            // NOTE: stable sort keeps the order of first occurrence for
+            // reactions with the same count
+            reactions.sort_by_key(|x| std::cmp::Reverse(x.count));
+        }
+        reactions
+    }
+}
+// e2b64f08 ends here
+
+// [[file:../../trajectory.note::1c59d7a2][1c59d7a2]]
+fn format_frame(frame: Option<usize>) -> String {
+    frame.map(|x| x.to_string()).unwrap_or_default()
+}
+
+impl ReactionSummary {
+    /// Return unique reactions in CSV format.
+    pub fn to_csv(&self, sort_by_count: bool) -> String {
+        let mut s = String::new();
+        writeln!(s, "Reaction,Count,First frame,Last frame,Reverse count,Net count").unwrap();
+        for x in self.unique_reactions(sort_by_count) {
+            let (first, last) = (format_frame(x.first_frame), format_frame(x.last_frame));
+            writeln!(s, "{},{},{first},{last},{},{}", x.equation, x.count, x.reverse_count, x.net_count).unwrap();
+        }
+        s
+    }
+
+    /// Return unique reactions in Markdown table.
+    pub fn to_markdown(&self, sort_by_count: bool) -> String {
+        let mut s = String::new();
+        writeln!(s, "| Reaction | Count | First frame | Last frame | Reverse count | Net count |").unwrap();
+        writeln!(s, "|---|---:|---:|---:|---:|---:|").unwrap();
+        for x in self.unique_reactions(sort_by_count) {
+            let (first, last) = (format_frame(x.first_frame), format_frame(x.last_frame));
+            let (equation, count, reverse, net) = (x.equation, x.count, x.reverse_count, x.net_count);
+            writeln!(s, "| {equation} | {count} | {first} | {last} | {reverse} | {net} |").unwrap();
+        }
+        s
+    }
+
+    /// Write unique reactions into file `f`. The format is determined by
+    /// file extension: `csv` for CSV, `md` for Markdown, and Parquet
+    /// format otherwise.
+    pub fn write_file(&self, f: &Path, sort_by_count: bool) -> Result<()> {
+        match f.extension().and_then(|x| x.to_str()) {
+            Some("csv") => gut::fs::write_to_file(f, &self.to_csv(sort_by_count))?,
+            Some("md") => gut::fs::write_to_file(f, &self.to_markdown(sort_by_count))?,
+            _ => {
+                let mut writer = SimpleParquetFileWriter::new(f);
+                writer.write_row_group(&self.unique_reactions(sort_by_count))?;
+                writer.close()?;
+            }
+        }
+        Ok(())
+    }
+}
+// 1c59d7a2 ends here
+
+// [[file:../../trajectory.note::94f0e6b3][94f0e6b3]]
+#[test]
+fn test_reaction_summary() -> Result<()> {
+    use super::io::test_reaction as reaction;
+
+    let reactions = vec![
+        reaction(5, &["h", "h"], &["h2"]),
+        reaction(9, &["h2"], &["h", "h"]),
+        reaction(12, &["h", "h"], &["h2"]),
+        reaction(15, &["h2"], &["h", "h"]),
+        reaction(20, &["h2"], &["h", "h"]),
+        reaction(21, &["h"], &[]),
+    ];
+    let summary = ReactionSummary::from_reactions(&reactions);
+    let unique = summary.unique_reactions(false);
+    assert_eq!(unique.len(), 2);
+    assert_eq!(unique[0].equation, "2 H => H2");
+    assert_eq!(unique[0].count, 2);
+    assert_eq!(unique[0].first_frame, Some(5));
+    assert_eq!(unique[0].last_frame, Some(12));
+    assert_eq!(unique[0].reverse_count, 3);
+    assert_eq!(unique[0].net_count, -1);
+
+    let unique = summary.unique_reactions(true);
+    assert_eq!(unique[0].equation, "H2 => 2 H");
+    assert_eq!(unique[0].count, 3);
+    assert!(summary.to_markdown(true).lines().nth(2).unwrap().starts_with("| H2 => 2 H | 3 |"));
+
+    Ok(())
+}
+// 94f0e6b3 ends here
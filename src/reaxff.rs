@@ -0,0 +1,137 @@
+// [[file:../trajectory.note::5e8a2c71][5e8a2c71]]
+use crate::common::*;
+
+use text_parser::TextReader;
+// 5e8a2c71 ends here
+
+// [[file:../trajectory.note::a3d6f0b9][a3d6f0b9]]
+/// Bond orders of one frame written by LAMMPS `fix reaxff/bonds`.
+#[derive(Debug, Default, Clone)]
+pub struct ReaxffBondsFrame {
+    /// # Timestep
+    pub timestep: usize,
+    /// Bonded atom pairs (atom ids) with bond orders. Each bond
+    /// appears only once with the smaller atom id first.
+    pub bonds: Vec<([usize; 2], f64)>,
+}
+
+impl ReaxffBondsFrame {
+    /// Return atom pairs with bond order no less than `cutoff`.
+    pub fn bonds_above(&self, cutoff: f64) -> impl Iterator<Item = [usize; 2]> + '_ {
+        self.bonds.iter().filter(move |(_, bo)| *bo >= cutoff).map(|(pair, _)| *pair)
+    }
+}
+
+/// Parse one frame in ReaxFF bonds file. Each atom line has columns
+/// like: id type nb id_1...id_nb mol bo_1...bo_nb abo nlp q
+fn parse_reaxff_bonds_frame(s: &str) -> Result<ReaxffBondsFrame> {
+    let mut frame = ReaxffBondsFrame::default();
+    let mut timestep = None;
+    for line in s.lines() {
+        let line = line.trim();
+        if let Some(x) = line.strip_prefix("# Timestep") {
+            timestep = Some(x.trim().parse().with_context(|| format!("invalid timestep line: {line:?}"))?);
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let items: Vec<_> = line.split_whitespace().collect();
+        let parse_id = |x: &str| x.parse::<usize>().with_context(|| format!("invalid atom line: {line:?}"));
+        ensure!(items.len() >= 3, "invalid atom line: {line:?}");
+        let id = parse_id(items[0])?;
+        let nb = parse_id(items[2])?;
+        ensure!(items.len() >= 4 + 2 * nb, "invalid atom line: {line:?}");
+        for k in 0..nb {
+            let j = parse_id(items[3 + k])?;
+            let bo: f64 = items[4 + nb + k]
+                .parse()
+                .with_context(|| format!("invalid bond order: {line:?}"))?;
+            // NOTE: each bond is listed for both atoms
+            if id < j {
+                frame.bonds.push(([id, j], bo));
+            }
+        }
+    }
+    frame.timestep = timestep.ok_or(format_err!("no timestep found in ReaxFF bonds frame"))?;
+    Ok(frame)
+}
+
+/// Parse ReaxFF bonds file written by LAMMPS `fix reaxff/bonds`,
+/// returning an iterator over frames.
+pub fn parse_reaxff_bonds_file(f: &Path) -> Result<impl Iterator<Item = Result<ReaxffBondsFrame>>> {
+    let reader = TextReader::try_from_path(f)?;
+    let frames = reader
+        .partitions_preceded(|line| line.starts_with("# Timestep"))
+        .map(|data| parse_reaxff_bonds_frame(&data));
+    Ok(frames)
+}
+// a3d6f0b9 ends here
+
+// [[file:../trajectory.note::64f1d8a2][64f1d8a2]]
+/// Read ReaxFF bonds frames matching trajectory frames in order.
+pub struct ReaxffBondsReader {
+    frames: Box<dyn Iterator<Item = Result<ReaxffBondsFrame>>>,
+    nread: usize,
+}
+
+impl ReaxffBondsReader {
+    pub fn new(f: &Path) -> Result<Self> {
+        let frames = parse_reaxff_bonds_file(f)?;
+        Ok(Self {
+            frames: Box::new(frames),
+            nread: 0,
+        })
+    }
+
+    /// Return the next frame matching `timestep`. If `timestep` is
+    /// None, the frame is matched by order, with `step_by - 1` frames
+    /// skipped between two calls.
+    pub fn next_frame(&mut self, timestep: Option<usize>, step_by: usize) -> Result<ReaxffBondsFrame> {
+        let nskip = if self.nread == 0 { 0 } else { step_by - 1 };
+        self.nread += 1;
+        let t = match timestep {
+            Some(t) => t,
+            None => return self.frames.nth(nskip).ok_or(format_err!("no more ReaxFF bonds frames"))?,
+        };
+
+        for frame in self.frames.by_ref() {
+            let frame = frame?;
+            if frame.timestep == t {
+                return Ok(frame);
+            } else if frame.timestep > t {
+                bail!("no ReaxFF bonds frame for timestep {t}");
+            }
+        }
+        bail!("no ReaxFF bonds frame for timestep {t}");
+    }
+}
+// 64f1d8a2 ends here
+
+// [[file:../trajectory.note::0c5b7e42][0c5b7e42]]
+#[test]
+fn test_parse_reaxff_bonds() -> Result<()> {
+    let txt = "# Timestep 100
+#
+# Number of particles 4
+#
+# Max number of bonds per atom 2 with coarse bond order cutoff 0.300
+# Particle connection table and bond orders
+# id type nb id_1...id_nb mol bo_1...bo_nb abo nlp q
+ 1 1 1 2 0 0.950 0.950 0.000 0.120
+ 2 2 2 1 3 0 0.950 0.350 1.300 0.000 -0.220
+ 3 2 1 2 0 0.350 0.350 0.000 -0.100
+ 4 1 0 0 0.000 0.000 0.200
+#
+";
+    let frame = parse_reaxff_bonds_frame(txt)?;
+    assert_eq!(frame.timestep, 100);
+    assert_eq!(frame.bonds.len(), 2);
+    assert_eq!(frame.bonds[0], ([1, 2], 0.95));
+    assert_eq!(frame.bonds_above(0.3).count(), 2);
+    assert_eq!(frame.bonds_above(0.5).collect_vec(), vec![[1, 2]]);
+
+    Ok(())
+}
+// 0c5b7e42 ends here